@@ -1,52 +1,206 @@
-use std::sync::mpsc::{channel, Sender};
+use std::any::Any;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::mpsc::{channel, SendError, Sender};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use simbelmyne_chess::{board::Board, movegen::moves::Move};
 
+use crate::variant::Variant;
+
 mod simbelmyne;
 mod engine;
 mod executable;
+mod parser;
 
 pub use simbelmyne::*;
 pub use engine::*;
 pub use executable::*;
+pub use parser::*;
 
-type PerftResult = Vec<(Move, usize)>;
+/// A split perft result: every move in the position, along with the number of
+/// nodes found below it.
+pub type PerftResult = Vec<(Move, usize)>;
 
+/// Anything that can produce a split perft result for a given position
 pub trait Perft {
     fn perft(
         &mut self,
         board: Board,
         depth: usize,
     ) -> anyhow::Result<PerftResult>;
+
+    /// The total number of nodes at the given depth. Backends that can count 
+    /// them faster than by splitting them up per move can override this.
+    fn total(&mut self, board: Board, depth: usize) -> anyhow::Result<usize> {
+        let moves = self.perft(board, depth)?;
+        Ok(moves.iter().map(|(_, count)| count).sum())
+    }
+
+    /// Play a move on the board, following the same rules the backend uses to
+    /// generate its moves. The reference backend's rules are the ones used to
+    /// walk down the perft tree.
+    fn play_move(&self, board: &Board, mv: Move) -> Board {
+        board.play_move(mv)
+    }
+
+    /// Map a move reported by another backend onto the move this backend
+    /// generates for it, so the two can be lined up even when they write
+    /// castles differently. See [`Variant::normalize`].
+    fn normalize(&self, board: &Board, mv: Move) -> Move {
+        Variant::Standard.normalize(board, mv)
+    }
+
+    /// Any lines of output that were skipped during the last run, because 
+    /// they couldn't be parsed.
+    fn take_warnings(&mut self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// The name the backend goes by, if it reports one (e.g., a UCI engine's
+    /// `id name`)
+    fn name(&self) -> Option<String> {
+        None
+    }
+
+    /// What was sent to and received from the backend during the last run,
+    /// one line at a time.
+    fn transcript(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+impl<T: Perft + ?Sized> Perft for Box<T> {
+    fn perft(
+        &mut self,
+        board: Board,
+        depth: usize,
+    ) -> anyhow::Result<PerftResult> {
+        (**self).perft(board, depth)
+    }
+
+    fn total(&mut self, board: Board, depth: usize) -> anyhow::Result<usize> {
+        (**self).total(board, depth)
+    }
+
+    fn play_move(&self, board: &Board, mv: Move) -> Board {
+        (**self).play_move(board, mv)
+    }
+
+    fn normalize(&self, board: &Board, mv: Move) -> Move {
+        (**self).normalize(board, mv)
+    }
+
+    fn take_warnings(&mut self) -> Vec<String> {
+        (**self).take_warnings()
+    }
+
+    fn name(&self) -> Option<String> {
+        (**self).name()
+    }
+
+    fn transcript(&self) -> Vec<String> {
+        (**self).transcript()
+    }
+}
+
+/// How long a backend took to produce a split perft result
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timing {
+    /// The position that was searched
+    pub board: Board,
+    pub depth: usize,
+
+    /// Wall-clock time, from sending the request to getting the full result
+    pub elapsed: Duration,
+
+    /// The total number of nodes found
+    pub nodes: usize,
+}
+
+impl Timing {
+    /// Nodes per second
+    pub fn nps(&self) -> f64 {
+        self.nodes as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
 }
 
 struct PerftRequest {
     board: Board,
     depth: usize,
-    result_buf: Arc<Mutex<PerftResult>>,
+    result_buf: Arc<Mutex<Divide>>,
 }
 
 pub struct PerftThread {
-    tx: Sender<PerftRequest>
+    tx: Sender<PerftRequest>,
+
+    /// The name of the backend running on the thread, if it reports one
+    name: Option<String>,
 }
 
 impl PerftThread {
     pub fn new<T: Perft + Send + 'static>(mut runner: T) -> Self {
         let (tx, rx) = channel::<PerftRequest>();
+        let name = runner.name();
 
         std::thread::spawn(move || {
             for req in rx {
-                let result = runner.perft(req.board, req.depth).unwrap();
+                // Don't bring down the whole session when a backend fails (or
+                // even panics), but report the failure along with the other
+                // warnings.
+                let start = Instant::now();
+                let result = catch_unwind(AssertUnwindSafe(|| runner.perft(req.board, req.depth)))
+                    .unwrap_or_else(|panic| Err(anyhow::anyhow!(
+                        "The backend panicked: {}",
+                        panic_message(panic.as_ref())
+                    )));
+                let elapsed = start.elapsed();
+
+                let mut result = match result {
+                    Ok(moves) => Divide {
+                        timing: Some(Timing {
+                            board: req.board,
+                            depth: req.depth,
+                            elapsed,
+                            nodes: moves.iter().map(|(_, count)| count).sum(),
+                        }),
+                        moves,
+                        warnings: runner.take_warnings(),
+                        ..Divide::default()
+                    },
+
+                    Err(err) => Divide { warnings: vec![format!("{err:#}")], ..Divide::default() },
+                };
+
+                result.transcript = runner.transcript();
+
                 let mut buf = req.result_buf.lock().unwrap();
                 *buf = result;
             }
         });
 
-        Self { tx }
+        Self { tx, name }
+    }
+
+    /// The name of the backend, if it reports one
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
     }
 
-    pub fn run(&mut self, board: Board, depth: usize, result_buf: Arc<Mutex<PerftResult>>) {
-        self.tx.send(PerftRequest { board, depth, result_buf }).unwrap();
+    pub fn run(&mut self, board: Board, depth: usize, result_buf: Arc<Mutex<Divide>>) {
+        if let Err(SendError(req)) = self.tx.send(PerftRequest { board, depth, result_buf }) {
+            *req.result_buf.lock().unwrap() = Divide {
+                warnings: vec![String::from("The backend's thread has stopped")],
+                ..Divide::default()
+            };
+        }
     }
 }
+
+/// The message a panic was raised with, if it has one
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    panic.downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("no message")
+}
@@ -1,102 +1,178 @@
-use std::{io::{self, BufRead, BufReader, Read, Write}, path::PathBuf, process::{Child, ChildStdin, ChildStdout, Command, Stdio}};
-use anyhow::anyhow;
+use std::{fmt::Display, io::{self, BufRead, BufReader, Write}, path::PathBuf, process::{Child, ChildStdin, ChildStdout, Command, Stdio}};
 
-use simbelmyne_chess::{board::Board, movegen::moves::Move};
+use anyhow::Context;
 
-use super::{Perft, PerftResult};
+use simbelmyne_chess::board::Board;
+
+use crate::variant::{CastlingNotation, Variant};
+
+use super::{Divide, Perft, PerftResult};
 
 pub struct Engine {
     child: Child,
     output: BufReader<ChildStdout>,
     input: ChildStdin,
+    variant: Variant,
+
+    /// How to write the castling rights of Chess960 positions
+    castling: CastlingNotation,
+
+    /// The name the engine reported with `id name`
+    name: Option<String>,
+
+    /// Every line sent to (`>`) and received from (`<`) the engine, for the
+    /// setup and the last run
+    transcript: Vec<String>,
+
+    /// How much of the transcript is part of the setup, once the first run
+    /// starts
+    setup_len: Option<usize>,
+
+    /// The lines that couldn't be parsed during the last run
+    warnings: Vec<String>,
 }
 
 impl Engine {
-    pub fn new(path: PathBuf) -> io::Result<Engine> {
-        let mut child = Command::new(path)
+    pub fn new(path: PathBuf) -> anyhow::Result<Engine> {
+        Self::with_args(path, &[])
+    }
+
+    /// Start the engine with extra command line arguments
+    pub fn with_args(path: PathBuf, args: &[String]) -> anyhow::Result<Engine> {
+        let mut child = Command::new(&path)
+            .args(args)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::null())
-            .spawn()?;
+            .spawn()
+            .with_context(|| format!("Failed to run {}", path.display()))?;
+
+        let output = BufReader::new(child.stdout.take().expect("stdout not captured"));
+        let input = child.stdin.take().expect("stdin not captured");
+
+        let mut engine = Engine { 
+            child, 
+            input, 
+            output, 
+            variant: Variant::Standard, 
+            castling: CastlingNotation::default(),
+            name: None,
+            transcript: Vec::new(),
+            setup_len: None,
+            warnings: Vec::new(),
+        };
+
+        engine.send("uci")?;
+
+        while let Some(line) = engine.receive()? {
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = Some(name.trim().to_owned());
+            }
+
+            if line == "uciok" {
+                break;
+            }
+        }
 
-        let mut output = BufReader::new(child.stdout.take().expect("stdout not captured"));
-        let mut input = child.stdin.take().expect("stdin not captured");
+        // // Initialize engine
+        engine.send("isready")?;
+        engine.wait_until_ready()?;
 
-        writeln!(input, "uci")?;
-        input.flush()?;
+        Ok(engine)
+    }
 
+    /// Switch the engine over to another variant. For Chess960, this sets the
+    /// `UCI_Chess960` option, and positions are sent as Shredder-FEN.
+    pub fn set_variant(&mut self, variant: Variant) -> io::Result<()> {
+        let chess960 = matches!(variant, Variant::Chess960(_));
+        self.send(&format!("setoption name UCI_Chess960 value {chess960}"))?;
 
-        for line in output.by_ref().lines() {
-            if let Ok(line) = line {
-                // println!("{line}");
-                if &line == "uciok" {
-                    break;
-                }
-            }
+        self.variant = variant;
+        Ok(())
+    }
+
+    /// Send Chess960 castling rights in another notation, for engines that 
+    /// don't understand Shredder-FEN.
+    pub fn set_castling_notation(&mut self, notation: CastlingNotation) {
+        self.castling = notation;
+    }
+
+    /// Set a UCI option, e.g., `Hash` or `Threads`
+    pub fn set_option(&mut self, name: &str, value: impl Display) -> io::Result<()> {
+        self.send(&format!("setoption name {name} value {value}"))
+    }
+
+    /// Send a command to the engine
+    fn send(&mut self, command: &str) -> io::Result<()> {
+        writeln!(self.input, "{command}")?;
+        self.input.flush()?;
+
+        self.transcript.push(format!("> {}", command.trim_end()));
+        Ok(())
+    }
+
+    /// Read the next line of output, if the engine hasn't gone away
+    fn receive(&mut self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+
+        if self.output.read_line(&mut line)? == 0 {
+            return Ok(None);
         }
 
-        // // Initialize engine
-        writeln!(input, "isready")?;
-        input.flush()?;
-
-        for line in output.by_ref().lines() {
-            if let Ok(line) = line {
-                if &line == "readyok" {
-                    break;
-                }
+        let line = line.trim_end_matches(['\n', '\r']).to_owned();
+        self.transcript.push(format!("< {line}"));
+        Ok(Some(line))
+    }
+
+    fn wait_until_ready(&mut self) -> io::Result<()> {
+        while let Some(line) = self.receive()? {
+            if line == "readyok" {
+                break;
             }
         }
 
-        Ok(Engine { child, input, output })
+        Ok(())
     }
 }
 
 impl Perft for Engine {
     fn perft(&mut self, board: Board, depth: usize) -> anyhow::Result<PerftResult> {
-        // Set position
-        writeln!(self.input, "position fen {}", board.to_fen())?;
-        self.input.flush()?;
+        // Only keep the transcript of the last run, on top of the setup
+        let setup_len = *self.setup_len.get_or_insert(self.transcript.len());
+        self.transcript.truncate(setup_len);
 
-        writeln!(self.input, "isready")?;
-        self.input.flush()?;
+        // Set position
+        self.send(&format!("position fen {}", self.variant.to_fen_as(&board, self.castling)))?;
+        self.send("isready")?;
+        self.wait_until_ready()?;
 
-        for line in self.output.by_ref().lines() {
-            if let Ok(line) = line {
-                if &line == "readyok" {
-                    break;
-                }
-            }
-        }
-
-        writeln!(self.input, "go perft {}\n", depth)?;
-        self.input.flush()?;
+        self.send(&format!("go perft {}\n", depth))?;
 
         // parse child counts
-        let mut move_list: PerftResult = Vec::new();
-
-        for line in self.output.by_ref().lines() {
-            let line = line?;
+        let mut divide = Divide::default();
 
+        while let Some(line) = self.receive()? {
             if line.trim().is_empty() {
                 break;
-            } else {
-                let mut parts = line.trim().split(": ");
+            }
 
-                let move_ = parts.next()
-                    .ok_or(anyhow!("Failed to parse perft output {line}"))?
-                    .to_string();
+            divide.push_line(&line);
+        }
 
-                let count = parts
-                    .next()
-                    .ok_or(anyhow!("Failed to parse perft output {line}"))?
-                    .parse().map_err(|_| anyhow!("failed to parse perft output {line}"))?;
+        self.warnings = divide.warnings;
+        Ok(divide.moves)
+    }
 
-                let mv: Move = move_.parse()?;
-                move_list.push((mv, count));
-            }
-        }
+    fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    fn name(&self) -> Option<String> {
+        self.name.clone()
+    }
 
-        Ok(move_list)
+    fn transcript(&self) -> Vec<String> {
+        self.transcript.clone()
     }
 }
 
@@ -105,5 +181,3 @@ impl Drop for Engine {
         let _ = self.child.kill();
     }
 }
-
-
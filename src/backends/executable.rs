@@ -1,52 +1,94 @@
 use std::{path::PathBuf, process::Command};
-use anyhow::anyhow;
 
-use simbelmyne_chess::{board::Board, movegen::moves::Move};
+use anyhow::Context;
 
-use super::{Perft, PerftResult};
+use simbelmyne_chess::board::Board;
+
+use crate::variant::{CastlingNotation, Variant};
+
+use super::{parse_divide, Perft, PerftResult};
 
 pub struct Executable {
-    path: PathBuf
+    path: PathBuf,
+
+    /// Arguments to pass before the FEN and depth
+    args: Vec<String>,
+    variant: Variant,
+
+    /// How to write the castling rights of Chess960 positions
+    castling: CastlingNotation,
+
+    /// The lines that couldn't be parsed during the last run
+    warnings: Vec<String>,
+
+    /// The command line of the last run (`>`), and its output (`<`)
+    transcript: Vec<String>,
 }
 
 impl Executable {
     pub fn new(path: PathBuf) -> Self {
-        Self { path }
+        Self::with_args(path, &[])
+    }
+
+    /// Run the executable with extra arguments, passed before the FEN and 
+    /// depth.
+    pub fn with_args(path: PathBuf, args: &[String]) -> Self {
+        Self { 
+            path, 
+            args: args.to_vec(),
+            variant: Variant::Standard, 
+            castling: CastlingNotation::default(),
+            warnings: Vec::new(),
+            transcript: Vec::new(),
+        }
+    }
+
+    /// Pass positions in another variant to the executable. For Chess960, 
+    /// positions are passed as Shredder-FEN.
+    pub fn set_variant(&mut self, variant: Variant) {
+        self.variant = variant;
+    }
+
+    /// Pass Chess960 castling rights in another notation, for scripts that 
+    /// don't understand Shredder-FEN.
+    pub fn set_castling_notation(&mut self, notation: CastlingNotation) {
+        self.castling = notation;
     }
 }
 
 impl Perft for Executable {
     fn perft(&mut self, board: Board, depth: usize) -> anyhow::Result<PerftResult> {
+        let fen = self.variant.to_fen_as(&board, self.castling);
+
+        let command_line = std::iter::once(self.path.display().to_string())
+            .chain(self.args.iter().cloned())
+            .chain([format!("\"{fen}\""), depth.to_string()])
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        self.transcript = vec![format!("> {command_line}")];
+
         let output_bytes = Command::new(&self.path)
-            .arg(board.to_fen())
+            .args(&self.args)
+            .arg(fen)
             .arg(depth.to_string())
             .output()
-            .unwrap();
+            .with_context(|| format!("Failed to run {}", self.path.display()))?;
 
         let output = String::from_utf8(output_bytes.stdout)?;
-        let mut move_list: PerftResult = Vec::new();
-
-        for line in output.lines() {
-            if line.trim().is_empty() {
-                break;
-            } else {
-                let mut parts = line.trim().split(": ");
-
-                let move_ = parts.next()
-                    .ok_or(anyhow!("Failed to parse perft output {line}"))?
-                    .to_string();
-
-                let count = parts
-                    .next()
-                    .ok_or(anyhow!("Failed to parse perft output {line}"))?
-                    .parse().map_err(|_| anyhow!("failed to parse perft output {line}"))?;
-
-                let mv: Move = move_.parse()?;
-                move_list.push((mv, count));
-            }
-        }
+        self.transcript.extend(output.lines().map(|line| format!("< {line}")));
+
+        let divide = parse_divide(output.lines());
+        self.warnings = divide.warnings;
 
-        Ok(move_list)
+        Ok(divide.moves)
+    }
+
+    fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings)
     }
-}
 
+    fn transcript(&self) -> Vec<String> {
+        self.transcript.clone()
+    }
+}
@@ -0,0 +1,104 @@
+use anyhow::anyhow;
+
+use simbelmyne_chess::movegen::moves::Move;
+
+use super::{PerftResult, Timing};
+
+/// Parse a single line of split perft output, of the form 
+/// `<coordinate move>: <number of nodes>`
+pub fn parse_divide_line(line: &str) -> anyhow::Result<(Move, usize)> {
+    let mut parts = line.trim().split(": ");
+
+    let move_ = parts.next()
+        .ok_or(anyhow!("Failed to parse perft output {line}"))?
+        .to_string();
+
+    let count = parts
+        .next()
+        .ok_or(anyhow!("Failed to parse perft output {line}"))?
+        .parse().map_err(|_| anyhow!("failed to parse perft output {line}"))?;
+
+    // Squares and promotion pieces are accepted in either case
+    let mv: Move = move_
+        .to_ascii_lowercase()
+        .parse()
+        .map_err(|_| anyhow!("Failed to parse move '{move_}' in perft output {line}"))?;
+
+    Ok((mv, count))
+}
+
+/// Split perft output, along with the lines that had to be skipped because
+/// they couldn't be parsed.
+#[derive(Debug, Default, Clone)]
+pub struct Divide {
+    pub moves: PerftResult,
+    pub warnings: Vec<String>,
+
+    /// What was sent to and received from the backend to get this result
+    pub transcript: Vec<String>,
+
+    /// How long the backend took, if the run succeeded
+    pub timing: Option<Timing>,
+}
+
+impl Divide {
+    /// Parse a single line of split perft output. Lines that can't be parsed
+    /// are recorded as warnings, rather than failing the entire parse.
+    pub fn push_line(&mut self, line: &str) {
+        match parse_divide_line(line) {
+            Ok(entry) => self.moves.push(entry),
+            Err(err) => self.warnings.push(err.to_string()),
+        }
+    }
+}
+
+/// Parse split perft output, up until the first blank line.
+///
+/// Anything after the first blank line (typically the total node count) is
+/// ignored.
+pub fn parse_divide<I, S>(lines: I) -> Divide
+where 
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut divide = Divide::default();
+
+    for line in lines {
+        let line = line.as_ref();
+
+        if line.trim().is_empty() {
+            break;
+        }
+
+        divide.push_line(line);
+    }
+
+    divide
+}
+
+#[cfg(test)]
+mod tests {
+    use simbelmyne_chess::piece::PieceType;
+
+    use super::*;
+
+    #[test]
+    fn promotions_in_either_case() {
+        let (lower, count) = parse_divide_line("e7e8q: 5").unwrap();
+        let (upper, _) = parse_divide_line("E7E8Q: 5").unwrap();
+
+        assert_eq!(lower.get_promo_type(), Some(PieceType::Queen));
+        assert_eq!(upper, lower);
+        assert_eq!(count, 5);
+    }
+
+    #[test]
+    fn unparseable_lines_become_warnings() {
+        let divide = parse_divide(["e2e4: 20", "e9e4: 1", "d2d4 20", "g1f3: lots", "", "Nodes: 41"]);
+
+        assert_eq!(divide.moves.len(), 1);
+        assert_eq!(divide.moves[0].0.to_string(), "e2e4");
+        assert_eq!(divide.moves[0].1, 20);
+        assert_eq!(divide.warnings.len(), 3);
+    }
+}
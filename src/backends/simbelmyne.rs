@@ -1,10 +1,52 @@
-use simbelmyne_chess::board::Board;
+use std::sync::Arc;
 
-use crate::perft::perft_divide;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use simbelmyne_chess::{board::Board, movegen::moves::Move};
+
+use crate::perft::{perft, perft_divide, perft_divide_sequential, perft_sequential};
+use crate::variant::Variant;
 
 use super::{Perft, PerftResult};
 
-pub struct Simbelmyne {}
+/// The reference backend, built on the `simbelmyne-chess` move generator
+#[derive(Debug, Default, Clone)]
+pub struct Simbelmyne {
+    pub variant: Variant,
+    threads: Threads,
+}
+
+/// Which threads the reference runs on
+#[derive(Debug, Default, Clone)]
+enum Threads {
+    /// Rayon's global thread pool, with a thread for every core
+    #[default]
+    Global,
+
+    /// Only the thread the backend was called from
+    Single,
+
+    /// A thread pool of its own
+    Pool(Arc<ThreadPool>),
+}
+
+impl Simbelmyne {
+    pub fn new(variant: Variant) -> Self {
+        Self { variant, threads: Threads::Global }
+    }
+
+    /// Run on a thread pool of a given size, rather than on all cores, so
+    /// the reference doesn't compete with the engine under test for them. A
+    /// single thread runs everything on the calling thread.
+    pub fn with_threads(self, threads: usize) -> anyhow::Result<Self> {
+        let threads = match threads {
+            0 => anyhow::bail!("The reference needs at least one thread"),
+            1 => Threads::Single,
+            n => Threads::Pool(Arc::new(ThreadPoolBuilder::new().num_threads(n).build()?)),
+        };
+
+        Ok(Self { threads, ..self })
+    }
+}
 
 impl Perft for Simbelmyne {
     fn perft(
@@ -12,6 +54,30 @@ impl Perft for Simbelmyne {
         board: Board,
         depth: usize,
     ) -> anyhow::Result<PerftResult> {
-        Ok(perft_divide(board, depth))
+        let variant = self.variant;
+
+        match &self.threads {
+            Threads::Global => perft_divide(board, depth, variant),
+            Threads::Single => perft_divide_sequential(board, depth, variant),
+            Threads::Pool(pool) => pool.install(|| perft_divide(board, depth, variant)),
+        }
+    }
+
+    fn total(&mut self, board: Board, depth: usize) -> anyhow::Result<usize> {
+        let variant = self.variant;
+
+        Ok(match &self.threads {
+            Threads::Global => perft(board, depth, variant),
+            Threads::Single => perft_sequential(board, depth, variant),
+            Threads::Pool(pool) => pool.install(|| perft(board, depth, variant)),
+        })
+    }
+
+    fn play_move(&self, board: &Board, mv: Move) -> Board {
+        self.variant.play_move(board, mv)
+    }
+
+    fn normalize(&self, board: &Board, mv: Move) -> Move {
+        self.variant.normalize(board, mv)
     }
 }
@@ -0,0 +1,327 @@
+//! Benchmarking a backend on its perft speed, outside of the TUI.
+//!
+//! Every position is searched a number of times, and the timings are summed
+//! up as the mean, median and standard deviation of the time and nodes per
+//! second. The results can be saved as a baseline, to compare later runs
+//! against:
+//!
+//! ```toml
+//! backend = "Fake 1.2"
+//! runs = 5
+//!
+//! [[results]]
+//! fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+//! depth = 5
+//! nodes = 4865609
+//! time_ms = { mean = 1214.2, median = 1210.8, stddev = 9.4 }
+//! nps = { mean = 4007240.6, median = 4018518.4, stddev = 30824.7 }
+//! ```
+
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use clap::builder::RangedU64ValueParser;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::components::info_view::{format_duration, format_nps};
+
+#[derive(Debug, clap::Args)]
+pub struct BenchArgs {
+    /// A file with the positions to run, one FEN per line. The depth to run
+    /// each position at can be given as EPD operations (`;D5 4865609`), in
+    /// which case the deepest one is used, and the node count is checked
+    /// [default: the --fen at --depth]
+    #[arg(long)]
+    pub positions: Option<PathBuf>,
+
+    /// How many times to run every position
+    #[arg(long, default_value_t = 5, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pub runs: usize,
+
+    /// Benchmark the reference move generator instead of the engine under
+    /// test
+    #[arg(long)]
+    pub reference: bool,
+
+    /// Print the results as JSON
+    #[arg(long)]
+    pub json: bool,
+
+    /// Save the results to this file, to compare later runs against
+    #[arg(long)]
+    pub save_baseline: Option<PathBuf>,
+
+    /// Compare the results to a baseline saved with --save-baseline, and fail
+    /// if any position got slower
+    #[arg(long)]
+    pub baseline: Option<PathBuf>,
+
+    /// How much slower than the baseline a position may get before it counts
+    /// as a regression, in percent of the median time
+    #[arg(long, default_value_t = 5.0)]
+    pub tolerance: f64,
+}
+
+/// A position to benchmark, as read from a positions file
+#[derive(Debug, Clone)]
+pub struct BenchPosition {
+    pub fen: String,
+
+    /// The deepest depth listed for the position, if any
+    pub depth: Option<usize>,
+
+    /// The node count listed for each depth
+    pub counts: Vec<(usize, usize)>,
+}
+
+impl BenchPosition {
+    /// Parse a line of an EPD file, e.g.,
+    ///
+    /// ```text
+    /// <fen> ;D1 20 ;D2 400 ;id "start"
+    /// ```
+    ///
+    /// Operations other than the node counts are ignored. FENs without move
+    /// counters get them filled in.
+    fn parse(line: &str) -> anyhow::Result<Self> {
+        let mut parts = line.split(';');
+        let fen = parts.next().unwrap_or_default().trim();
+
+        let fen = match fen.split_whitespace().count() {
+            4 => format!("{fen} 0 1"),
+            _ => fen.to_owned(),
+        };
+
+        let counts = parts
+            .filter_map(|op| op.trim().strip_prefix('D'))
+            .map(|op| {
+                let (depth, count) = op.split_once(' ')
+                    .with_context(|| format!("Invalid node count ';D{op}'"))?;
+
+                let depth = depth.parse()
+                    .ok()
+                    .filter(|&depth: &usize| depth > 0)
+                    .with_context(|| format!("Invalid depth in ';D{op}'"))?;
+
+                let count = count.trim().parse()
+                    .with_context(|| format!("Invalid node count in ';D{op}'"))?;
+
+                Ok((depth, count))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let depth = counts.iter().map(|&(depth, _)| depth).max();
+
+        Ok(Self { fen, depth, counts })
+    }
+
+    /// The node count listed for a depth, if any
+    pub fn expected(&self, depth: usize) -> Option<usize> {
+        self.counts
+            .iter()
+            .find(|&&(d, _)| d == depth)
+            .map(|&(_, count)| count)
+    }
+}
+
+/// Read the positions to benchmark from an EPD file, skipping blank lines and
+/// comments starting with `#`.
+pub fn read_positions(path: &Path) -> anyhow::Result<Vec<BenchPosition>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read positions file {}", path.display()))?;
+
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(idx, line)| {
+            BenchPosition::parse(line)
+                .with_context(|| format!("Invalid position on line {} of {}", idx + 1, path.display()))
+        })
+        .collect()
+}
+
+/// A summary of a number of measurements
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Stats {
+    pub mean: f64,
+    pub median: f64,
+
+    /// The sample standard deviation, or zero for a single measurement
+    pub stddev: f64,
+}
+
+impl Stats {
+    pub fn new(samples: &[f64]) -> Self {
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+
+        let sorted = samples.iter().copied().sorted_by(f64::total_cmp).collect_vec();
+        let mid = sorted.len() / 2;
+
+        let median = if sorted.len() % 2 == 0 {
+            (sorted[mid - 1] + sorted[mid]) / 2.0
+        } else {
+            sorted[mid]
+        };
+
+        let stddev = if samples.len() > 1 {
+            let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+            variance.sqrt()
+        } else {
+            0.0
+        };
+
+        Self { mean, median, stddev }
+    }
+}
+
+/// The timings of a single position
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BenchResult {
+    pub fen: String,
+    pub depth: usize,
+    pub nodes: usize,
+
+    /// The time per run, in milliseconds
+    pub time_ms: Stats,
+
+    /// The nodes per second of every run
+    pub nps: Stats,
+}
+
+impl BenchResult {
+    /// Sum up the runs of a position, given the time of every run in
+    /// milliseconds.
+    pub fn new(fen: String, depth: usize, nodes: usize, times_ms: &[f64]) -> Self {
+        let nps = times_ms
+            .iter()
+            .map(|ms| nodes as f64 / (ms / 1000.0).max(f64::EPSILON))
+            .collect_vec();
+
+        Self {
+            fen,
+            depth,
+            nodes,
+            time_ms: Stats::new(times_ms),
+            nps: Stats::new(&nps),
+        }
+    }
+}
+
+/// The results of a benchmark run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Bench {
+    /// The name of the backend that was benchmarked
+    pub backend: String,
+
+    /// How many times every position was run
+    pub runs: usize,
+    pub results: Vec<BenchResult>,
+}
+
+impl Bench {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read baseline file {}", path.display()))?;
+
+        toml::from_str(&contents)
+            .with_context(|| format!("Invalid baseline file {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let contents = toml::to_string(self)?;
+
+        std::fs::write(path, contents)
+            .with_context(|| format!("Failed to write baseline file {}", path.display()))
+    }
+
+    /// Line up every result with the same position and depth in a baseline.
+    /// A position counts as a regression when its median time went up by more
+    /// than `tolerance` percent.
+    pub fn compare(&self, baseline: &Bench, tolerance: f64) -> Vec<Comparison> {
+        self.results
+            .iter()
+            .filter_map(|result| {
+                let before = baseline.results
+                    .iter()
+                    .find(|before| before.fen == result.fen && before.depth == result.depth)?;
+
+                let change = result.time_ms.median / before.time_ms.median - 1.0;
+
+                Some(Comparison {
+                    fen: result.fen.clone(),
+                    depth: result.depth,
+                    before_ms: before.time_ms.median,
+                    after_ms: result.time_ms.median,
+                    change,
+                    regressed: change * 100.0 > tolerance,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Display for Bench {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}, {} run(s) per position", self.backend, self.runs)?;
+
+        for result in &self.results {
+            let time = result.time_ms;
+            let nps = result.nps;
+
+            writeln!(f)?;
+            writeln!(f, "{} (depth {})", result.fen, result.depth)?;
+            writeln!(f, "  Nodes: {}", result.nodes)?;
+            writeln!(
+                f,
+                "  Time:  mean {}, median {}, stddev {}",
+                format_duration(time.mean),
+                format_duration(time.median),
+                format_duration(time.stddev),
+            )?;
+            writeln!(
+                f,
+                "  NPS:   mean {}, median {}, stddev {}",
+                format_nps(nps.mean),
+                format_nps(nps.median),
+                format_nps(nps.stddev),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// How the median time of a position changed since the baseline
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    pub fen: String,
+    pub depth: usize,
+    pub before_ms: f64,
+    pub after_ms: f64,
+
+    /// The relative change in median time, e.g., `0.1` for 10% slower
+    pub change: f64,
+    pub regressed: bool,
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} (depth {}): {} -> {} ({:+.1}%){}",
+            self.fen,
+            self.depth,
+            format_duration(self.before_ms),
+            format_duration(self.after_ms),
+            self.change * 100.0,
+            if self.regressed { ", slower" } else { "" },
+        )
+    }
+}
@@ -0,0 +1,111 @@
+//! Copying text to the clipboard through the terminal, using the OSC 52 
+//! escape sequence. This works over SSH, and doesn't need a system clipboard
+//! on the machine pretty-perft runs on.
+//!
+//! Not every terminal supports OSC 52 (and there's no way to tell), so it can
+//! be turned off in the config file with `clipboard = "file"`, in which case 
+//! the text is written to a temporary file instead. The same happens when the
+//! text is too large for most terminals to accept.
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+/// How to get copied text out of the TUI
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClipboardMode {
+    /// Through the terminal, with the OSC 52 escape sequence
+    #[default]
+    Osc52,
+
+    /// Write the text to a file in the temp directory
+    File,
+}
+
+/// The things that can be copied
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Clip {
+    /// The FEN of the current position
+    Fen,
+
+    /// The UCI command that sets up the current position: the starting 
+    /// position, followed by the moves played since.
+    UciPosition,
+
+    /// The move table, lined up as plain text
+    Table,
+
+    /// The move table, as a Markdown table
+    MarkdownTable,
+}
+
+impl Clip {
+    pub fn label(self) -> &'static str {
+        match self {
+            Clip::Fen => "the FEN",
+            Clip::UciPosition => "the UCI position command",
+            Clip::Table => "the move table",
+            Clip::MarkdownTable => "the move table as Markdown",
+        }
+    }
+
+    /// The name of the file to write to, when not copying to the clipboard
+    fn file_name(self) -> &'static str {
+        match self {
+            Clip::Fen => "pretty-perft-fen.txt",
+            Clip::UciPosition => "pretty-perft-position.txt",
+            Clip::Table => "pretty-perft-table.txt",
+            Clip::MarkdownTable => "pretty-perft-table.md",
+        }
+    }
+}
+
+/// Where the copied text ended up
+pub enum Copied {
+    Clipboard,
+    File(PathBuf),
+}
+
+/// Most terminals cap the size of an OSC 52 sequence somewhere around here
+const MAX_OSC52_LEN: usize = 100_000;
+
+/// Copy the text to the clipboard, or to a file if that's not possible.
+pub fn copy(text: &str, clip: Clip, mode: ClipboardMode) -> anyhow::Result<Copied> {
+    let encoded = base64(text.as_bytes());
+
+    if mode == ClipboardMode::Osc52 && encoded.len() <= MAX_OSC52_LEN {
+        let mut stderr = std::io::stderr();
+        let written = write!(stderr, "\x1b]52;c;{encoded}\x07").and_then(|_| stderr.flush());
+
+        if written.is_ok() {
+            return Ok(Copied::Clipboard);
+        }
+    }
+
+    let path = std::env::temp_dir().join(clip.file_name());
+    std::fs::write(&path, text)?;
+    Ok(Copied::File(path))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
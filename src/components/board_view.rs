@@ -1,99 +1,343 @@
+use simbelmyne_chess::bitboard::Bitboard;
 use simbelmyne_chess::constants::LIGHT_SQUARES;
 use simbelmyne_chess::square::Square;
 use simbelmyne_chess::{board::Board, movegen::moves::Move};
 use simbelmyne_chess::piece::Piece;
 use itertools::Itertools;
 use ratatui::{
-    prelude::{Buffer, Constraint, Direction, Layout, Rect},
-    style::{Style, Stylize},
-    text::Line,
+    prelude::{Buffer, Constraint, Position, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
     widgets::{Block, Borders, Cell, Row, Table, Widget},
 };
 
+use pretty_perft::variant::Variant;
+
+use crate::components::centered;
+use crate::theme::{PieceStyle, Theme};
+
 pub struct BoardView {
     pub board: Board,
+
+    /// Needed to know where the castling kings and rooks are in Chess960
+    pub variant: Variant,
     pub highlight: Option<Move>,
+
+    /// The square clicked as the source of a move, waiting for a target
+    pub pending: Option<Square>,
+    pub overlays: Overlays,
+    pub theme: Theme,
+    pub pieces: PieceStyle,
+
+    /// Draw the board from Black's perspective, with rank 1 at the top
+    pub flipped: bool,
+}
+
+/// The different kinds of information that can be drawn on top of the board
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Overlay {
+    /// Squares attacked by the side not to move
+    Attacked,
+
+    /// Pieces of the side to move that are pinned, along with their pin rays
+    Pins,
+
+    /// Pieces checking the side to move
+    Checkers,
+
+    /// The en-passant square
+    EnPassant,
+
+    /// King and rook squares for the remaining castling rights
+    Castling,
+}
+
+impl Overlay {
+    pub const ALL: [Overlay; 5] = [
+        Overlay::Attacked,
+        Overlay::Pins,
+        Overlay::Checkers,
+        Overlay::EnPassant,
+        Overlay::Castling,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Overlay::Attacked => "attacked",
+            Overlay::Pins => "pins",
+            Overlay::Checkers => "checkers",
+            Overlay::EnPassant => "en-passant",
+            Overlay::Castling => "castling",
+        }
+    }
+}
+
+/// The set of overlays that are currently toggled on
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Overlays(u8);
+
+impl Overlays {
+    pub fn is_enabled(self, overlay: Overlay) -> bool {
+        self.0 & (1 << overlay as u8) != 0
+    }
+
+    pub fn toggle(&mut self, overlay: Overlay) {
+        self.0 ^= 1 << overlay as u8;
+    }
+}
+
+/// The squares to mark for each of the overlays, for a given board
+struct Markers {
+    attacked: Bitboard,
+    pinned: Bitboard,
+    pinrays: Bitboard,
+    checkers: Bitboard,
+    en_passant: Bitboard,
+    castling: Bitboard,
+}
+
+impl Markers {
+    fn new(board: &Board, variant: Variant, overlays: Overlays) -> Self {
+        let us = board.current;
+        let enabled = |overlay: Overlay, squares: Bitboard| {
+            if overlays.is_enabled(overlay) { squares } else { Bitboard::EMPTY }
+        };
+
+        // Don't count our own king as a blocker, so the squares behind it 
+        // (that it can't step back to) also show up as attacked.
+        let attacked = board.attacked_by::<false>(!us);
+        let pinrays = board.get_pinrays(us);
+        let pinned = pinrays & board.occupied_by(us);
+
+        let en_passant = board.en_passant
+            .map(Bitboard::from)
+            .unwrap_or(Bitboard::EMPTY);
+
+        Self {
+            attacked: enabled(Overlay::Attacked, attacked),
+            pinned: enabled(Overlay::Pins, pinned),
+            pinrays: enabled(Overlay::Pins, pinrays & !pinned),
+            checkers: enabled(Overlay::Checkers, board.get_checkers(us)),
+            en_passant: enabled(Overlay::EnPassant, en_passant),
+            castling: enabled(Overlay::Castling, variant.castling_squares(board)),
+        }
+    }
+
+    /// The background color for a square, if any of the overlays apply
+    fn background(&self, sq: Square, theme: &Theme) -> Option<Color> {
+        if self.checkers.contains(sq) {
+            Some(theme.checkers)
+        } else if self.pinned.contains(sq) {
+            Some(theme.pinned)
+        } else if self.en_passant.contains(sq) {
+            Some(theme.en_passant)
+        } else if self.castling.contains(sq) {
+            Some(theme.castling)
+        } else if self.pinrays.contains(sq) {
+            Some(theme.pinray)
+        } else {
+            None
+        }
+    }
+}
+
+impl BoardView {
+    /// The square drawn at the given position, if any, when the board is 
+    /// rendered in `area`.
+    pub fn square_at(area: Rect, flipped: bool, position: Position) -> Option<Square> {
+        let (rect, size) = board_rect(area);
+
+        if !rect.contains(position) {
+            return None;
+        }
+
+        let column = (position.x - rect.x) as usize / size.width;
+        let row = (position.y - rect.y) as usize / size.height;
+
+        // The outer rows and columns hold the labels
+        if !(1..=8).contains(&column) || !(1..=8).contains(&row) {
+            return None;
+        }
+
+        let (rank, file) = if flipped { (row - 1, 8 - column) } else { (8 - row, column - 1) };
+
+        Some(Square::from(8 * rank + file))
+    }
+
+    fn square_to_cell(&self, piece: Option<Piece>, attacked: bool, size: CellSize) -> Cell<'static> {
+        let marker = if attacked { "•" } else { "" };
+        let marker = Span::from(marker).fg(self.theme.attacked);
+
+        match piece {
+            Some(piece) => to_marked_cell(self.pieces.glyph(piece), marker, size)
+                .fg(self.theme.piece(piece)),
+            None => to_marked_cell(String::from(""), marker, size),
+        }
+    }
+}
+
+/// The dimensions of a single board cell, in terminal characters
+#[derive(Debug, Copy, Clone)]
+struct CellSize {
+    width: usize,
+    height: usize,
 }
 
-fn square_to_cell(piece: Option<Piece>) -> Cell<'static> {
-    match piece {
-        Some(piece) => to_padded_cell(piece.to_string()),
-        None => to_padded_cell(String::from("")),
+impl CellSize {
+    /// The number of cells along each side of the board, including labels
+    const CELLS: usize = 10;
+
+    /// Pick the largest cell that lets the board fit in the available area.
+    ///
+    /// Terminal characters are about twice as tall as they are wide, so we 
+    /// stick to cells of (2h - 1) x h characters to keep the squares square:
+    /// 1x1, 3x2, 5x3, 7x4, ...
+    fn fit(area: Rect) -> Self {
+        let max_width = area.width as usize / Self::CELLS;
+        let max_height = area.height as usize / Self::CELLS;
+        let height = max_height.min(max_width.div_ceil(2)).max(1);
+
+        Self { width: 2 * height - 1, height }
     }
 }
 
-const CELL_WIDTH: usize = 5;
-const CELL_HEIGHT: usize = 3;
+/// The area taken up by the board's cells (labels included) when rendered in
+/// `area`, along with the size of a single cell.
+fn board_rect(area: Rect) -> (Rect, CellSize) {
+    let inner = Block::new().borders(Borders::ALL).inner(area);
+    let size = CellSize::fit(inner);
+    let width = CellSize::CELLS * size.width;
+    let height = CellSize::CELLS * size.height;
 
-fn to_padded_cell(val: String) -> Cell<'static> {
-    let lines = vec![
-        vec![Line::from(""); CELL_HEIGHT / 2],
-        vec![Line::from(format!("{:^CELL_WIDTH$}", val))],
-        vec![Line::from(""); CELL_HEIGHT / 2],
-    ]
-    .concat();
+    (centered(inner, width as u16, height as u16), size)
+}
+
+fn to_padded_cell(val: String, size: CellSize) -> Cell<'static> {
+    to_marked_cell(val, Span::from(""), size)
+}
+
+/// Pad the value to fill the cell, with an optional marker in the top-left
+/// corner. If the cell is only a single line tall, the marker is only shown 
+/// when there is no value to show.
+fn to_marked_cell(val: String, marker: Span<'static>, size: CellSize) -> Cell<'static> {
+    let CellSize { width, height } = size;
+    let middle = height / 2;
+
+    let lines = (0..height)
+        .map(|line| {
+            if line == middle && (height > 1 || !val.is_empty()) {
+                Line::from(format!("{:^width$}", val))
+            } else if line == 0 {
+                Line::from(marker.clone())
+            } else {
+                Line::from("")
+            }
+        })
+        .collect_vec();
 
     Cell::from(lines)
 }
 
+impl BoardView {
+    /// The height the board panel needs to draw the largest board that fits 
+    /// in the given width, borders included.
+    pub fn preferred_height(width: u16) -> u16 {
+        let inner = Rect::new(0, 0, width.saturating_sub(2), u16::MAX);
+        let size = CellSize::fit(inner);
+
+        (CellSize::CELLS * size.height) as u16 + 2
+    }
+
+    /// Draw the board as plain text, the way it's shown on screen, to paste
+    /// into a bug report.
+    pub fn to_ascii(&self) -> String {
+        let (ranks, files) = draw_order(self.flipped);
+
+        let file_labels = files.iter()
+            .map(|&file| &Square::NAMES[file][..1])
+            .join(" ");
+
+        let border = format!("  +-{}-+", "-".repeat(2 * files.len() - 1));
+
+        let mut lines = vec![border.clone()];
+
+        for &rank in ranks.iter() {
+            let squares = files.iter()
+                .map(|&file| match self.board.get_at(Square::from(8 * rank + file)) {
+                    Some(piece) => PieceStyle::Ascii.glyph(piece),
+                    None => String::from("."),
+                })
+                .join(" ");
+
+            lines.push(format!("{} | {squares} |", rank + 1));
+        }
+
+        lines.push(border);
+        lines.push(format!("    {file_labels}"));
+
+        lines.join("\n")
+    }
+}
+
+/// The order in which to draw the ranks and files, from the top-left
+fn draw_order(flipped: bool) -> (Vec<usize>, Vec<usize>) {
+    if flipped {
+        ((0..8).collect_vec(), (0..8).rev().collect_vec())
+    } else {
+        ((0..8).rev().collect_vec(), (0..8).collect_vec())
+    }
+}
+
 impl Widget for BoardView {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let width = 10 * CELL_WIDTH;
-        let height = 10 * CELL_HEIGHT;
-
-        let rect = Layout::new(
-            Direction::Vertical, 
-            [
-                Constraint::Min((area.height - height as u16) / 2),
-                Constraint::Min(height as u16),
-                Constraint::Min((area.height - height as u16) / 2),
-            ])
-            .split(area)[1];
-
-        let rect = Layout::new(
-            Direction::Horizontal,
-            [
-                Constraint::Min((area.width - width as u16) / 2),
-                Constraint::Min(width as u16),
-                Constraint::Min((area.width - width as u16) / 2),
-            ])
-            .split(rect)[1];
-
-        let file_labels = vec!["", "a", "b", "c", "d", "e", "f", "g", "h", ""]
-            .into_iter()
-            .map(|label| to_padded_cell(label.to_owned()))
+        let border = Block::new()
+            .title("Board")
+            .borders(Borders::ALL)
+            .title_style(Style::new().fg(self.theme.text))
+            .border_style(Style::new().fg(self.theme.muted));
+
+        let (rect, size) = board_rect(area);
+
+        let (ranks, files) = draw_order(self.flipped);
+
+        let file_labels = files.iter()
+            .map(|&file| to_padded_cell(Square::NAMES[file][..1].to_owned(), size));
+
+        let file_labels = std::iter::once(to_padded_cell(String::new(), size))
+            .chain(file_labels)
+            .chain(std::iter::once(to_padded_cell(String::new(), size)))
             .collect_vec();
 
-        let file_labels = Row::new(file_labels).height(CELL_HEIGHT as u16).dark_gray();
+        let file_labels = Row::new(file_labels).height(size.height as u16).fg(self.theme.muted);
 
         let mut rows: Vec<Row> = Vec::new();
         // Push top heading
         rows.push(file_labels.clone());
 
+        let markers = Markers::new(&self.board, self.variant, self.overlays);
+
         let mut current_rank: Vec<Cell> = Vec::new();
-        let ranks = self.board.piece_list.into_iter().chunks(8);
-        let ranks = ranks
-            .into_iter()
-            .enumerate()
-            .collect_vec()
-            .into_iter()
-            .rev();
 
-        for (rank, squares) in ranks {
-            let rank_label = to_padded_cell((rank + 1).to_string()).dark_gray();
+        for &rank in ranks.iter() {
+            let rank_label = to_padded_cell((rank + 1).to_string(), size).fg(self.theme.muted);
             current_rank.push(rank_label.clone());
 
-            for (file, piece) in squares.enumerate() {
+            for &file in files.iter() {
                 let sq = Square::from(8 * rank + file);
+                let piece = self.board.get_at(sq);
+
+                let cell = self.square_to_cell(piece, markers.attacked.contains(sq), size);
 
-                let cell = if self.highlight.is_some_and(|mv| sq == mv.src()) {
-                    square_to_cell(piece).on_blue()
-                } else if self.highlight.is_some_and(|mv| sq == mv.tgt()) {
-                    square_to_cell(piece).on_blue()
+                let cell = if self.pending == Some(sq) {
+                    cell.bg(self.theme.accent).black()
+                } else if self.highlight.is_some_and(|mv| sq == mv.src() || sq == mv.tgt()) {
+                    cell.bg(self.theme.highlight)
+                } else if let Some(color) = markers.background(sq, &self.theme) {
+                    cell.bg(color).black()
                 } else if LIGHT_SQUARES.contains(sq) {
-                    square_to_cell(piece)
+                    cell.bg(self.theme.light_square)
                 } else {
-                    square_to_cell(piece).on_dark_gray()
+                    cell.bg(self.theme.dark_square)
                 };
 
                 current_rank.push(cell);
@@ -101,7 +345,7 @@ impl Widget for BoardView {
 
             current_rank.push(rank_label);
 
-            rows.push(Row::new(current_rank).height(CELL_HEIGHT as u16));
+            rows.push(Row::new(current_rank).height(size.height as u16));
             current_rank = Vec::new();
         }
 
@@ -110,15 +354,18 @@ impl Widget for BoardView {
 
         let table = Table::new(
             rows,
-            &[Constraint::Length(CELL_WIDTH as u16); 10]
+            &[Constraint::Length(size.width as u16); CellSize::CELLS]
         )
             .column_spacing(0);
 
-        let border = Block::new()
-            .title("Board")
-            .borders(Borders::ALL)
-            .title_style(Style::new().white())
-            .border_style(Style::new().dark_gray());
+        let legend = Overlay::ALL
+            .into_iter()
+            .filter(|&overlay| self.overlays.is_enabled(overlay))
+            .map(|overlay| overlay.label())
+            .join(", ");
+
+        let border = border
+            .title_bottom(if legend.is_empty() { String::new() } else { format!(" {legend} ") });
 
         Widget::render(border, area, buf);
         Widget::render(table, rect, buf);
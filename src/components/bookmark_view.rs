@@ -0,0 +1,60 @@
+use ratatui::{
+    prelude::{Buffer, Rect},
+    style::{Style, Stylize},
+    text::{Line, Text},
+    widgets::{Block, Borders, HighlightSpacing, List, ListItem, ListState, Padding, StatefulWidget, Widget},
+};
+
+use crate::session::Bookmark;
+use crate::theme::Theme;
+
+/// The list of bookmarked positions, shown in place of the details while 
+/// it's open.
+pub struct BookmarkView {
+    pub bookmarks: Vec<Bookmark>,
+    pub selected: usize,
+    pub theme: Theme,
+}
+
+impl Widget for BookmarkView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let border = Block::new()
+            .title(format!(" Bookmarks ({}) ", self.bookmarks.len()))
+            .borders(Borders::ALL)
+            .title_style(Style::new().fg(self.theme.text))
+            .border_style(Style::new().fg(self.theme.accent))
+            .padding(Padding::new(1, 1, 1, 1));
+
+        if self.bookmarks.is_empty() {
+            let empty = Text::from("Nothing bookmarked yet").fg(self.theme.muted);
+            Widget::render(empty, border.inner(area), buf);
+            border.render(area, buf);
+            return;
+        }
+
+        let items = self.bookmarks.iter().map(|bookmark| {
+            let note = if bookmark.note.is_empty() {
+                Line::from("No note").fg(self.theme.muted)
+            } else {
+                Line::from(bookmark.note.clone())
+            };
+
+            let moves = if bookmark.moves.is_empty() {
+                String::from("Starting position")
+            } else {
+                bookmark.moves.join(" ")
+            };
+
+            ListItem::new(vec![note, Line::from(moves).fg(self.theme.muted)])
+        });
+
+        let list = List::new(items)
+            .block(border)
+            .highlight_style(Style::new().fg(self.theme.accent))
+            .highlight_spacing(HighlightSpacing::Always)
+            .highlight_symbol("> ");
+
+        let mut state = ListState::default().with_selected(Some(self.selected));
+        StatefulWidget::render(list, area, buf, &mut state);
+    }
+}
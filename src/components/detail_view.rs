@@ -0,0 +1,67 @@
+use pretty_perft::explain::{Explanation, Verdict};
+use ratatui::{
+    prelude::{Buffer, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Padding, Paragraph, Widget, Wrap},
+};
+
+use crate::theme::Theme;
+
+pub struct DetailView {
+    pub mv: Option<String>,
+    pub explanation: Option<Explanation>,
+
+    /// Lines of backend output that had to be ignored
+    pub warnings: Vec<String>,
+    pub theme: Theme,
+}
+
+impl Widget for DetailView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let border = Block::new()
+            .title(" Details ")
+            .borders(Borders::ALL)
+            .title_style(Style::new().fg(self.theme.text))
+            .border_style(Style::new().fg(self.theme.muted))
+            .padding(Padding::new(2, 2, 1, 1));
+
+        let mut lines: Vec<Line> = Vec::new();
+
+        if let (Some(mv), Some(explanation)) = (self.mv, self.explanation) {
+            let color = match explanation.verdict {
+                Verdict::Agree => self.theme.muted,
+                Verdict::Deeper | Verdict::Invalid | Verdict::Duplicate => self.theme.warning,
+                Verdict::Illegal | Verdict::Missing => self.theme.mismatch,
+            };
+
+            let headline = Span::from(explanation.verdict.label()).fg(color);
+
+            lines.push(Line::from(vec![Span::from(format!("{mv} ")).bold(), headline]));
+
+            for reason in explanation.reasons {
+                lines.push(Line::from(""));
+                lines.push(Line::from(reason));
+            }
+        }
+
+        if !self.warnings.is_empty() {
+            if !lines.is_empty() {
+                lines.push(Line::from(""));
+            }
+
+            lines.push(Line::from(format!("{} warning(s)", self.warnings.len())).fg(self.theme.warning).bold());
+
+            for warning in self.warnings {
+                lines.push(Line::from(""));
+                lines.push(Line::from(warning).fg(self.theme.warning));
+            }
+        }
+
+        let paragraph = Paragraph::new(Text::from(lines))
+            .wrap(Wrap { trim: true })
+            .block(border);
+
+        paragraph.render(area, buf);
+    }
+}
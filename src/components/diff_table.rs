@@ -1,50 +1,264 @@
+use itertools::Itertools;
 use ratatui::widgets::{
     Block, Borders, HighlightSpacing, Padding, Row, StatefulWidget, Table, TableState, Widget,
 };
 use ratatui::{
-    prelude::{Alignment, Buffer, Constraint, Rect},
+    prelude::{Alignment, Buffer, Constraint, Position, Rect},
     style::{Style, Stylize},
 };
 
-use crate::tui::Diff;
-
-impl Diff {
-    fn to_table_row(&self) -> Row {
-        let mv = self.mv.to_string();
-        let found = self
-            .found
-            .map(|found| found.to_string())
-            .unwrap_or(String::from(""));
-        let expected = self
-            .expected
-            .map(|found| found.to_string())
-            .unwrap_or(String::from(""));
-
-        Row::new(vec![mv, found, expected])
+use pretty_perft::diff::Diff;
+use pretty_perft::explain::{ptype_name, why_invalid};
+use pretty_perft::san::{marker, san};
+use pretty_perft::variant::Variant;
+use simbelmyne_chess::board::Board;
+
+use crate::theme::Theme;
+
+/// The order in which to list the moves in the table
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum SortOrder {
+    /// Alphabetically, by the move's algebraic string
+    #[default]
+    Move,
+
+    /// Largest count found by the backend under test first
+    Found,
+
+    /// Largest count found by the reference backend first
+    Expected,
+
+    /// Largest absolute difference between the two counts first
+    Delta,
+}
+
+impl SortOrder {
+    /// Cycle through the sort orders
+    pub fn next(self) -> Self {
+        match self {
+            SortOrder::Move => SortOrder::Found,
+            SortOrder::Found => SortOrder::Expected,
+            SortOrder::Expected => SortOrder::Delta,
+            SortOrder::Delta => SortOrder::Move,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortOrder::Move => "move",
+            SortOrder::Found => "found",
+            SortOrder::Expected => "expected",
+            SortOrder::Delta => "delta",
+        }
+    }
+
+    /// Sort the diffs in place. Ties are kept in their original (alphabetical)
+    /// order.
+    pub fn sort(self, diffs: &mut [Diff]) {
+        match self {
+            SortOrder::Move => {},
+            SortOrder::Found => diffs.sort_by_key(|diff| std::cmp::Reverse(diff.found)),
+            SortOrder::Expected => diffs.sort_by_key(|diff| std::cmp::Reverse(diff.expected)),
+            SortOrder::Delta => diffs.sort_by_key(|diff| std::cmp::Reverse(diff.delta().abs())),
+        }
+    }
+}
+
+/// Check whether a row matches a search query.
+///
+/// Every word in the query should fuzzily match (i.e., appear as a 
+/// subsequence, ignoring case) either the UCI string, the SAN, the name of the
+/// moving piece or the target square of the move.
+pub fn matches_query(board: &Board, variant: Variant, diff: &Diff, query: &str) -> bool {
+    let mut keys = vec![diff.mv.to_string(), diff.mv.tgt().to_string()];
+    keys.extend(san(board, variant, diff.mv));
+    keys.extend(board.get_at(diff.mv.src()).map(|piece| ptype_name(piece.piece_type()).to_owned()));
+
+    query
+        .split_whitespace()
+        .all(|word| keys.iter().any(|key| is_subsequence(word, key)))
+}
+
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut haystack = haystack.chars().flat_map(char::to_lowercase);
+
+    needle
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|c| haystack.any(|h| h == c))
+}
+
+/// Check whether the backend under test reported a move that doesn't even
+/// correspond to a pseudo-legal move on the board.
+fn is_invalid(board: &Board, variant: Variant, diff: &Diff) -> bool {
+    diff.expected.is_none() && why_invalid(board, variant, diff.mv).is_some()
+}
+
+/// The count for a move, followed by the counts for any times the move was
+/// repeated, e.g. `20/20`.
+fn counts(count: Option<usize>, duplicates: &[usize]) -> String {
+    count.iter().chain(duplicates).join("/")
+}
+
+/// The column headers of the table
+const HEADER: [&str; 6] = ["Move", "SAN", "Type", "Found", "Expected", "Delta"];
+
+/// The contents of a row of the table
+fn to_cells(board: &Board, variant: Variant, diff: &Diff) -> [String; 6] {
+    let mv = diff.mv.to_string();
+    let san = san(board, variant, diff.mv).unwrap_or_default();
+    let marker = if diff.is_duplicate() {
+        String::from("dup")
+    } else if is_invalid(board, variant, diff) {
+        String::from("inv")
+    } else {
+        marker(board, variant, diff.mv)
+    };
+
+    let found = counts(diff.found, &diff.found_duplicates);
+    let expected = counts(diff.expected, &diff.expected_duplicates);
+    let delta = match diff.delta() {
+        0 => String::from(""),
+        delta => format!("{delta:+}"),
+    };
+
+    [mv, san, marker, found, expected, delta]
+}
+
+fn to_table_row(board: &Board, variant: Variant, diff: &Diff) -> Row<'static> {
+    Row::new(to_cells(board, variant, diff))
+}
+
+/// The table as plain text, with the columns lined up
+pub fn to_text(board: &Board, variant: Variant, diffs: &[Diff]) -> String {
+    let rows = diffs.iter().map(|diff| to_cells(board, variant, diff)).collect_vec();
+    let header = HEADER.map(String::from);
+
+    let widths: [usize; 6] = std::array::from_fn(|col| {
+        std::iter::once(&header)
+            .chain(&rows)
+            .map(|row| row[col].chars().count())
+            .max()
+            .unwrap_or(0)
+    });
+
+    std::iter::once(&header)
+        .chain(&rows)
+        .map(|row| {
+            let line = row.iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{cell:<width$}"))
+                .join("  ");
+
+            format!("{}\n", line.trim_end())
+        })
+        .collect()
+}
+
+/// The table as a Markdown table, with the counts aligned to the right
+pub fn to_markdown(board: &Board, variant: Variant, diffs: &[Diff]) -> String {
+    let mut lines = vec![
+        format!("| {} |", HEADER.join(" | ")),
+        String::from("| --- | --- | --- | ---: | ---: | ---: |"),
+    ];
+
+    for diff in diffs {
+        lines.push(format!("| {} |", to_cells(board, variant, diff).join(" | ")));
+    }
+
+    lines.iter().map(|line| format!("{line}\n")).collect()
+}
+
+/// Leave some breathing room around the table, unless space is tight
+fn table_padding(area: Rect) -> Padding {
+    if area.height >= 20 {
+        Padding::new(2, 2, 2, 2)
+    } else {
+        Padding::new(2, 2, 1, 1)
     }
 }
 
 pub struct DiffTable {
+    /// The position the moves are played from
+    pub board: Board,
+    pub variant: Variant,
     pub diffs: Vec<Diff>,
     pub selected: usize,
+
+    /// How many rows the table is scrolled down
+    pub offset: usize,
+    pub sort: SortOrder,
+    pub mismatches_only: bool,
+
+    /// The current search query, if any
+    pub search: Option<String>,
+
+    /// Whether the search query is still being typed
+    pub searching: bool,
+    pub theme: Theme,
+}
+
+impl DiffTable {
+    /// Scroll the table as little as possible to bring the selected row into
+    /// view, when it's rendered in `area`.
+    pub fn scroll(area: Rect, selected: usize, offset: usize) -> usize {
+        let visible = rows_area(area).height.max(1) as usize;
+        offset.clamp(selected.saturating_sub(visible - 1), selected)
+    }
+
+    /// The index of the row drawn at the given position, if any, when the 
+    /// table is rendered in `area` with `len` rows, scrolled down by `offset`.
+    pub fn row_at(area: Rect, offset: usize, len: usize, position: Position) -> Option<usize> {
+        let rows = rows_area(area);
+
+        if !rows.contains(position) {
+            return None;
+        }
+
+        let idx = offset + (position.y - rows.y) as usize;
+        (idx < len).then_some(idx)
+    }
+}
+
+/// The area taken up by the rows of the table, below the header
+fn rows_area(area: Rect) -> Rect {
+    let inner = Block::new().padding(table_padding(area)).inner(area);
+    Rect { y: inner.y + 1, height: inner.height.saturating_sub(1), ..inner }
 }
 
 impl Widget for DiffTable {
     fn render(self, area: Rect, buf: &mut Buffer) {
+        let mut settings = vec![format!("by {}", self.sort.label())];
+        if self.mismatches_only {
+            settings.push(String::from("mismatches only"));
+        }
+
+        if let Some(query) = &self.search {
+            let cursor = if self.searching { "_" } else { "" };
+            settings.push(format!("/{query}{cursor}"));
+        }
+
         let border = Block::default()
             .borders(Borders::ALL)
             .title(" Moves ")
+            .title_bottom(format!(" {} ", settings.join(", ")))
             .title_alignment(Alignment::Left)
-            .border_style(Style::new().dark_gray())
-            .title_style(Style::new().white())
+            .border_style(Style::new().fg(self.theme.muted))
+            .title_style(Style::new().fg(self.theme.text))
             .padding(Padding::new(3, 3, 2, 2));
 
-        let mut table_state = TableState::default().with_selected(Some(self.selected));
+        let mut table_state = TableState::default()
+            .with_offset(self.offset)
+            .with_selected(Some(self.selected));
         let rows = self.diffs.iter().map(|diff| {
-            diff.to_table_row().style(if diff.found == diff.expected {
-                Style::default().dark_gray()
+            to_table_row(&self.board, self.variant, diff).style(if !diff.is_mismatch() {
+                Style::default().fg(self.theme.muted)
+            } else if diff.is_duplicate() {
+                Style::default().fg(self.theme.warning).bold()
+            } else if is_invalid(&self.board, self.variant, diff) {
+                Style::default().fg(self.theme.warning)
             } else {
-                Style::default().red()
+                Style::default().fg(self.theme.mismatch)
             })
         });
 
@@ -52,14 +266,17 @@ impl Widget for DiffTable {
             rows,
             &[
                 Constraint::Length(5),
-                Constraint::Length(10),
-                Constraint::Length(10),
+                Constraint::Length(7),
+                Constraint::Length(5),
+                Constraint::Length(9),
+                Constraint::Length(9),
+                Constraint::Length(7),
             ],
         )
-        .header(Row::new(vec!["Move", "Found", "Expected"]).bold().blue())
-        .block(Block::new().padding(Padding::new(2, 2, 2, 2)))
-        .column_spacing(3)
-        .highlight_style(Style::default().white())
+        .header(Row::new(HEADER).bold().fg(self.theme.accent))
+        .block(Block::new().padding(table_padding(area)))
+        .column_spacing(2)
+        .highlight_style(Style::default().fg(self.theme.text))
         .highlight_spacing(HighlightSpacing::Always)
         .highlight_symbol("> ");
 
@@ -0,0 +1,98 @@
+use itertools::Itertools;
+use ratatui::{
+    prelude::{Buffer, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Clear, Padding, Paragraph, Widget},
+};
+
+use crate::keymap::{Group, Keymap};
+use crate::theme::Theme;
+use crate::tui::Message;
+
+use super::centered;
+
+/// What the mouse does, which can't be rebound
+const MOUSE: [(&str, &str); 4] = [
+    ("Click", "Select a move in the table"),
+    ("Double-click", "Play a move in the table"),
+    ("Wheel", "Move the selection"),
+    ("Click, click", "Play a move from one square to another"),
+];
+
+/// A modal listing every key binding, grouped by the part of the app it
+/// belongs to.
+pub struct HelpView {
+    pub keymap: Keymap,
+    pub theme: Theme,
+}
+
+impl Widget for HelpView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let groups = Group::ALL
+            .into_iter()
+            .map(|group| (group, self.keymap.group(group)))
+            .filter(|(_, bindings)| !bindings.is_empty())
+            .collect_vec();
+
+        // Line up the descriptions, leaving room for the longest list of keys
+        let keys_width = groups
+            .iter()
+            .flat_map(|(_, bindings)| bindings)
+            .map(|(keys, _)| keys.iter().join(", ").chars().count())
+            .chain(MOUSE.map(|(action, _)| action.len()))
+            .max()
+            .unwrap_or(0)
+            + 2;
+
+        let mut lines: Vec<Line> = Vec::new();
+
+        for (group, bindings) in groups {
+            if !lines.is_empty() {
+                lines.push(Line::from(""));
+            }
+
+            lines.push(Line::from(group.title()).bold().fg(self.theme.text));
+
+            for (keys, message) in bindings {
+                let keys = keys.iter().join(", ");
+
+                lines.push(Line::from(vec![
+                    Span::from(format!("  {keys:<keys_width$}")).fg(self.theme.accent),
+                    Span::from(message.description()).fg(self.theme.muted),
+                ]));
+            }
+        }
+
+        lines.push(Line::from(""));
+        lines.push(Line::from("Mouse").bold().fg(self.theme.text));
+
+        for (action, description) in MOUSE {
+            lines.push(Line::from(vec![
+                Span::from(format!("  {action:<keys_width$}")).fg(self.theme.accent),
+                Span::from(description).fg(self.theme.muted),
+            ]));
+        }
+
+        let width = lines.iter().map(Line::width).max().unwrap_or(0) + 6;
+        let height = lines.len() + 4;
+        let area = centered(area, width as u16, height as u16);
+
+        let close_keys = self.keymap
+            .keys_for(Message::ToggleHelp)
+            .into_iter()
+            .chain(self.keymap.keys_for(Message::Quit))
+            .join(", ");
+
+        let border = Block::new()
+            .title(" Help ")
+            .title_bottom(format!(" {close_keys} to close "))
+            .borders(Borders::ALL)
+            .title_style(Style::new().fg(self.theme.text))
+            .border_style(Style::new().fg(self.theme.accent))
+            .padding(Padding::new(2, 2, 1, 1));
+
+        Clear.render(area, buf);
+        Paragraph::new(Text::from(lines)).block(border).render(area, buf);
+    }
+}
@@ -4,6 +4,14 @@ use ratatui::{
     widgets::{Block, Borders, Cell, Padding, Row, Table, Widget},
 };
 
+use itertools::Itertools;
+use pretty_perft::backends::Timing;
+
+use crate::theme::Theme;
+
+/// How many of the previous timings of a position to show
+const HISTORY_LEN: usize = 6;
+
 pub struct InfoView {
     pub starting_pos: String,
     pub current_pos: String,
@@ -11,38 +19,75 @@ pub struct InfoView {
     pub current_depth: usize,
     pub total_found: usize,
     pub total_expected: usize,
+
+    /// How long the backend under test and the reference took, once both are 
+    /// done
+    pub timing: Option<(Timing, Timing)>,
+
+    /// How long the backend under test took on this position, every time it
+    /// was searched, in milliseconds
+    pub history: Vec<f64>,
+
+    /// Notes on the session, shown along the bottom border
+    pub notes: String,
+    pub theme: Theme,
 }
 
 impl Widget for InfoView {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let starting_fen = Row::new(vec![
-            Cell::from("Starting position").blue(),
-            Cell::from(format!("{}", self.starting_pos)),
+            Cell::from("Starting position").fg(self.theme.accent),
+            Cell::from(self.starting_pos),
         ]);
 
         let current_fen = Row::new(vec![
-            Cell::from("Current position").blue(),
-            Cell::from(format!("{}", self.current_pos)),
+            Cell::from("Current position").fg(self.theme.accent),
+            Cell::from(self.current_pos),
         ]);
 
         let search_depth = Row::new(vec![
-            Cell::from("Search depth").blue(),
+            Cell::from("Search depth").fg(self.theme.accent),
             Cell::from(format!("{}", self.search_depth)),
         ]);
 
         let current_depth = Row::new(vec![
-            Cell::from("Current depth").blue(),
+            Cell::from("Current depth").fg(self.theme.accent),
             Cell::from(format!("{}", self.current_depth)),
         ]);
 
+        let (found_timing, expected_timing) = match self.timing {
+            Some((found, expected)) => (format_timing(&found), format_timing(&expected)),
+            None => (String::new(), String::new()),
+        };
+
         let total_found = Row::new(vec![
-            Cell::from("Total found").blue(),
-            Cell::from(format!("{}", self.total_found)),
+            Cell::from("Total found").fg(self.theme.accent),
+            Cell::from(format!("{}{found_timing}", self.total_found)),
         ]);
 
         let total_expected = Row::new(vec![
-            Cell::from("Total expected").blue(),
-            Cell::from(format!("{}", self.total_expected)),
+            Cell::from("Total expected").fg(self.theme.accent),
+            Cell::from(format!("{}{expected_timing}", self.total_expected)),
+        ]);
+
+        let mut speed = match self.timing {
+            Some((found, expected)) => format!("{:.2}x the reference", found.nps() / expected.nps()),
+            None => String::from("Running..."),
+        };
+
+        if self.history.len() > 1 {
+            let history = self.history
+                .iter()
+                .skip(self.history.len().saturating_sub(HISTORY_LEN))
+                .map(|&ms| format_duration(ms))
+                .join(", ");
+
+            speed.push_str(&format!(" (history: {history})"));
+        }
+
+        let speed = Row::new(vec![
+            Cell::from("Speed").fg(self.theme.accent),
+            Cell::from(speed),
         ]);
 
         let table = Table::new(
@@ -53,6 +98,7 @@ impl Widget for InfoView {
                 current_depth,
                 total_found,
                 total_expected,
+                speed,
             ],
             &[Constraint::Min(20), Constraint::Min(100)],
         )
@@ -60,12 +106,37 @@ impl Widget for InfoView {
         .block(
             Block::new()
                 .title(" Information ")
+                .title_bottom(if self.notes.is_empty() { String::new() } else { format!(" {} ", self.notes) })
                 .borders(Borders::ALL)
-                .title_style(Style::new().white())
-                .border_style(Style::new().dark_gray())
+                .title_style(Style::new().fg(self.theme.text))
+                .border_style(Style::new().fg(self.theme.muted))
                 .padding(Padding::new(1, 1, 1, 1)),
         );
 
         Widget::render(table, area, buf);
     }
 }
+
+/// The time and nodes per second of a run, to follow its node count
+fn format_timing(timing: &Timing) -> String {
+    let ms = timing.elapsed.as_secs_f64() * 1000.0;
+    format!(" in {} ({})", format_duration(ms), format_nps(timing.nps()))
+}
+
+pub fn format_duration(ms: f64) -> String {
+    if ms < 1000.0 {
+        format!("{ms:.1} ms")
+    } else {
+        format!("{:.2} s", ms / 1000.0)
+    }
+}
+
+pub fn format_nps(nps: f64) -> String {
+    if nps >= 1e6 {
+        format!("{:.2} Mnps", nps / 1e6)
+    } else if nps >= 1e3 {
+        format!("{:.1} knps", nps / 1e3)
+    } else {
+        format!("{nps:.0} nps")
+    }
+}
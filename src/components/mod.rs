@@ -6,6 +6,9 @@ use ratatui::layout::Layout;
 pub mod board_view;
 pub mod info_view;
 pub mod diff_table;
+pub mod detail_view;
+pub mod help_view;
+pub mod bookmark_view;
 
 pub fn centered(container: Rect, width: u16, height: u16) -> Rect {
     let width = if width > container.width { container.width } else { width };
@@ -19,14 +22,12 @@ pub fn centered(container: Rect, width: u16, height: u16) -> Rect {
         ])
         .split(container)[1];
 
-    let centered_rect = Layout::default()
+    Layout::default()
         .direction(Direction::Horizontal)
         .constraints([
             Constraint::Length((container.width - width) / 2),
             Constraint::Length(width),
             Constraint::Length((container.width - width) / 2),
         ])
-        .split(vertically_centered_rect)[1];
-
-    centered_rect
+        .split(vertically_centered_rect)[1]
 }
@@ -0,0 +1,268 @@
+//! Settings that can be provided through a TOML config file, rather than on 
+//! the command line.
+//!
+//! The config file is looked up in `$XDG_CONFIG_HOME/pretty-perft/config.toml`
+//! (falling back to `~/.config/pretty-perft/config.toml`). A project-local
+//! `.pretty-perft.toml`, in the current directory or any of its parents, is 
+//! layered on top of that, and the command line on top of both. An example:
+//!
+//! ```toml
+//! depth = 6
+//! threads = 4
+//! profile = "mine"
+//! pieces = "unicode"
+//! theme = "colorblind"
+//!
+//! [profiles.mine]
+//! engine = "~/dev/engine/target/release/engine"
+//! options = { Hash = 64, Threads = 1 }
+//!
+//! [profiles.script]
+//! command = "./perft.sh"
+//! args = ["--divide"]
+//! castling = "x-fen"
+//!
+//! [colors]
+//! mismatch = "#d55e00"
+//! dark_square = "dark-gray"
+//!
+//! [keys]
+//! quit = ["q", "ctrl-c"]
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use pretty_perft::variant::CastlingNotation;
+
+use crate::clipboard::ClipboardMode;
+use crate::keymap::{KeyOverrides, KeymapPreset};
+use crate::theme::{ColorOverrides, PieceStyle, ThemeName};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileConfig {
+    /// The search depth, in ply
+    pub depth: Option<usize>,
+
+    /// The starting position
+    pub fen: Option<String>,
+
+    /// Path to a UCI engine to test
+    pub engine: Option<PathBuf>,
+
+    /// Path to a stand-alone perft script to test
+    pub command: Option<PathBuf>,
+
+    /// Play Chess960
+    pub chess960: Option<bool>,
+
+    /// Always draw the board from the perspective of the side to move
+    pub orient_to_move: Option<bool>,
+
+    /// The maximum number of positions to compare when looking for a 
+    /// reproducer
+    pub max_positions: Option<usize>,
+
+    /// The number of threads the reference move generator runs on
+    pub threads: Option<usize>,
+
+    /// The engine profile to use when none is picked on the command line
+    pub profile: Option<String>,
+
+    /// Named engines to test, along with how to talk to them
+    pub profiles: BTreeMap<String, Profile>,
+
+    /// How to draw the pieces on the board
+    pub pieces: Option<PieceStyle>,
+
+    /// The built-in theme to start from
+    pub theme: Option<ThemeName>,
+
+    /// Individual colors to override on top of the theme
+    pub colors: ColorOverrides,
+
+    /// The set of key bindings to start from
+    pub keymap: Option<KeymapPreset>,
+
+    /// Individual actions to bind to other keys
+    pub keys: KeyOverrides,
+
+    /// Whether to copy to the clipboard through the terminal, or to a file
+    pub clipboard: Option<ClipboardMode>,
+}
+
+/// An engine (or script) to test, and how to talk to it
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    /// Path to a UCI engine
+    pub engine: Option<PathBuf>,
+
+    /// Path to a stand-alone perft script
+    pub command: Option<PathBuf>,
+
+    /// Extra arguments to start the engine or script with
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+
+    /// UCI options to set before running perft
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub options: BTreeMap<String, UciValue>,
+
+    /// How to write the castling rights of Chess960 positions
+    pub castling: CastlingNotation,
+}
+
+/// The value of a UCI option
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum UciValue {
+    Bool(bool),
+    Integer(i64),
+    Text(String),
+}
+
+impl Display for UciValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UciValue::Bool(value) => write!(f, "{value}"),
+            UciValue::Integer(value) => write!(f, "{value}"),
+            UciValue::Text(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+/// The name of the project-local config file
+const LOCAL_FILE: &str = ".pretty-perft.toml";
+
+impl FileConfig {
+    /// The default location of the config file, if we can figure one out
+    pub fn default_path() -> Option<PathBuf> {
+        let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home_dir().map(|home| home.join(".config")))?;
+
+        Some(config_dir.join("pretty-perft").join("config.toml"))
+    }
+
+    /// The nearest project-local config file, in the current directory or 
+    /// any of its parents
+    pub fn local_path() -> Option<PathBuf> {
+        let current_dir = std::env::current_dir().ok()?;
+
+        current_dir
+            .ancestors()
+            .map(|dir| dir.join(LOCAL_FILE))
+            .find(|path| path.is_file())
+    }
+
+    /// Load the config file at the given path.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        Self::from_table(read_table(path)?)
+    }
+
+    /// Load the config files at the default locations, if there are any, 
+    /// with the project-local file taking precedence.
+    pub fn load_default() -> anyhow::Result<Self> {
+        let mut table = toml::Table::new();
+
+        for path in [Self::default_path(), Self::local_path()].into_iter().flatten() {
+            if path.is_file() {
+                merge(&mut table, read_table(&path)?);
+            }
+        }
+
+        Self::from_table(table)
+    }
+
+    fn from_table(table: toml::Table) -> anyhow::Result<Self> {
+        Ok(toml::Value::Table(table).try_into()?)
+    }
+
+    /// Look up an engine profile by name
+    pub fn profile(&self, name: &str) -> anyhow::Result<&Profile> {
+        self.profiles.get(name).ok_or_else(|| {
+            anyhow!(
+                "No profile named '{name}' in the config file (available: {})", 
+                self.profiles.keys().join(", ")
+            )
+        })
+    }
+}
+
+/// Read a config file, checking it on its own so any errors point at the file
+/// they're in.
+fn read_table(path: &Path) -> anyhow::Result<toml::Table> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file {}", path.display()))?;
+
+    let mut table: toml::Table = toml::from_str(&contents)
+        .with_context(|| format!("Invalid config file {}", path.display()))?;
+
+    FileConfig::from_table(table.clone())
+        .with_context(|| format!("Invalid config file {}", path.display()))?;
+
+    if let Some(dir) = path.parent() {
+        resolve_paths(&mut table, dir);
+    }
+
+    Ok(table)
+}
+
+/// Merge one config file into another, key by key, with the values in 
+/// `overlay` taking precedence.
+fn merge(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => merge(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Paths to engines and scripts are relative to the config file they're in, 
+/// and may start with `~`. Bare names (e.g., `stockfish`) are left alone, so 
+/// they're looked up in the `PATH`.
+fn resolve_paths(table: &mut toml::Table, dir: &Path) {
+    for key in ["engine", "command"] {
+        if let Some(toml::Value::String(path)) = table.get_mut(key) {
+            *path = resolve_path(path, dir).to_string_lossy().into_owned();
+        }
+    }
+
+    if let Some(toml::Value::Table(profiles)) = table.get_mut("profiles") {
+        for (_, profile) in profiles.iter_mut() {
+            if let toml::Value::Table(profile) = profile {
+                resolve_paths(profile, dir);
+            }
+        }
+    }
+}
+
+fn resolve_path(path: &str, dir: &Path) -> PathBuf {
+    if let Some(rest) = path.strip_prefix("~/") {
+        if let Some(home) = home_dir() {
+            return home.join(rest);
+        }
+    }
+
+    let path = Path::new(path);
+
+    if path.components().count() > 1 {
+        dir.join(path)
+    } else {
+        path.to_path_buf()
+    }
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(PathBuf::from)
+}
@@ -0,0 +1,238 @@
+//! Logic for lining up two split perft results and walking down the perft 
+//! tree along the moves where they disagree.
+
+use std::collections::BTreeMap;
+
+use simbelmyne_chess::{board::Board, movegen::moves::Move};
+
+use crate::backends::{Perft, PerftResult};
+use crate::perft::check_divide_depth;
+
+/// A single row in the comparison between two split perft results.
+///
+/// `found` holds the count reported by the backend under test, `expected` 
+/// holds the count reported by the reference backend. Either of them is `None`
+/// when the corresponding backend did not generate the move at all.
+///
+/// A backend that reports the same move more than once has the counts for the
+/// repeats stored in `found_duplicates` or `expected_duplicates`.
+#[derive(Debug, Clone)]
+pub struct Diff {
+    pub mv: Move,
+    pub found: Option<usize>,
+    pub expected: Option<usize>,
+    pub found_duplicates: Vec<usize>,
+    pub expected_duplicates: Vec<usize>,
+}
+
+impl Diff {
+    /// Check whether both backends agree on this move
+    pub fn is_mismatch(&self) -> bool {
+        self.found != self.expected || self.is_duplicate()
+    }
+
+    /// Check whether either backend reported this move more than once
+    pub fn is_duplicate(&self) -> bool {
+        !self.found_duplicates.is_empty() || !self.expected_duplicates.is_empty()
+    }
+
+    /// The number of nodes the backend under test reported for this move, 
+    /// summed over all the times it reported it.
+    pub fn found_total(&self) -> usize {
+        self.found.unwrap_or(0) + self.found_duplicates.iter().sum::<usize>()
+    }
+
+    /// The number of nodes the reference reported for this move, summed over
+    /// all the times it reported it.
+    pub fn expected_total(&self) -> usize {
+        self.expected.unwrap_or(0) + self.expected_duplicates.iter().sum::<usize>()
+    }
+
+    /// How many more nodes the backend under test found than the reference, 
+    /// counting a missing move as zero nodes.
+    pub fn delta(&self) -> i64 {
+        self.found_total() as i64 - self.expected_total() as i64
+    }
+}
+
+/// Line up the moves in two split perft results, sorted by their algebraic 
+/// string.
+pub fn diff(found: &PerftResult, expected: &PerftResult) -> Vec<Diff> {
+    let mut results: BTreeMap<String, Diff> = BTreeMap::new();
+
+    // Insert all of the found moves, keyed by their algebraic string
+    for (mv, count) in found.iter() {
+        let diff = results.entry(mv.to_string()).or_insert(Diff {
+            mv: *mv,
+            found: None,
+            expected: None,
+            found_duplicates: Vec::new(),
+            expected_duplicates: Vec::new(),
+        });
+
+        match diff.found {
+            Some(_) => diff.found_duplicates.push(*count),
+            None => diff.found = Some(*count),
+        }
+    }
+
+    // Fill in the expected counts, adding any moves that were missed.
+    //
+    // Moves parsed from the backend under test only carry a source and target
+    // square, so prefer the reference's move, which also knows about double 
+    // pushes, castles, en-passant captures, etc... when playing it on a board.
+    for (mv, count) in expected.iter() {
+        let diff = results.entry(mv.to_string()).or_insert(Diff {
+            mv: *mv,
+            found: None,
+            expected: None,
+            found_duplicates: Vec::new(),
+            expected_duplicates: Vec::new(),
+        });
+
+        diff.mv = *mv;
+
+        match diff.expected {
+            Some(_) => diff.expected_duplicates.push(*count),
+            None => diff.expected = Some(*count),
+        }
+    }
+
+    results.into_values().collect()
+}
+
+/// Run a split perft on both backends and line up the results.
+///
+/// `found` is the backend under test, `expected` is the reference.
+pub fn compare<A: Perft, B: Perft>(
+    board: Board,
+    depth: usize,
+    found: &mut A,
+    expected: &mut B,
+) -> anyhow::Result<Vec<Diff>> {
+    check_divide_depth(depth)?;
+    let found_moves = found.perft(board, depth)?;
+    let expected_moves = expected.perft(board, depth)?;
+
+    Ok(compare_results(&board, &found_moves, &expected_moves, expected))
+}
+
+/// Line up split perft results that were already computed on `board`.
+///
+/// The moves found by the backend under test are first mapped onto the 
+/// reference's moves, so differences in notation don't show up as mismatches.
+pub fn compare_results<B: Perft>(
+    board: &Board,
+    found: &PerftResult,
+    expected: &PerftResult,
+    reference: &B,
+) -> Vec<Diff> {
+    let found = found
+        .iter()
+        .map(|&(mv, count)| (reference.normalize(board, mv), count))
+        .collect();
+
+    diff(&found, expected)
+}
+
+/// The result of walking down the perft tree along mismatching moves.
+#[derive(Debug, Clone)]
+pub struct Descent {
+    /// The moves played from the root to get to the final position
+    pub path: Vec<Move>,
+
+    /// The final position
+    pub board: Board,
+
+    /// The split perft depth at the final position
+    pub depth: usize,
+
+    /// The comparison at the final position
+    pub diffs: Vec<Diff>,
+}
+
+/// Walk down the perft tree, starting from `board`, by repeatedly playing the 
+/// first move that both backends generated, but for which they report 
+/// different counts.
+///
+/// The descent stops when the depth reaches 1, or when there are no more 
+/// such moves to follow. In the latter case, the final diffs either agree
+/// completely, or contain a move that only one of the backends generated.
+pub fn descend<A: Perft, B: Perft>(
+    board: Board,
+    depth: usize,
+    found: &mut A,
+    expected: &mut B,
+) -> anyhow::Result<Descent> {
+    let mut descent = Descent {
+        path: Vec::new(),
+        board,
+        depth,
+        diffs: compare(board, depth, found, expected)?,
+    };
+
+    while descent.depth > 1 {
+        let next = descent.diffs
+            .iter()
+            .find(|diff| diff.is_mismatch() && diff.found.is_some() && diff.expected.is_some());
+
+        let Some(next) = next else { break };
+
+        descent.path.push(next.mv);
+        descent.board = expected.play_move(&descent.board, next.mv);
+        descent.depth -= 1;
+        descent.diffs = compare(descent.board, descent.depth, found, expected)?;
+    }
+
+    Ok(descent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(moves: &[(&str, usize)]) -> PerftResult {
+        moves.iter().map(|(mv, count)| (mv.parse().unwrap(), *count)).collect()
+    }
+
+    #[test]
+    fn collects_duplicates() {
+        let found = result(&[("e2e4", 20), ("d2d4", 20), ("e2e4", 21), ("e2e4", 22)]);
+        let expected = result(&[("e2e4", 20), ("d2d4", 20), ("d2d4", 20)]);
+        let diffs = diff(&found, &expected);
+
+        assert_eq!(diffs.len(), 2);
+
+        let d2d4 = &diffs[0];
+        assert_eq!(d2d4.mv.to_string(), "d2d4");
+        assert_eq!(d2d4.found, Some(20));
+        assert_eq!(d2d4.expected, Some(20));
+        assert_eq!(d2d4.expected_duplicates, vec![20]);
+        assert!(d2d4.is_mismatch());
+
+        let e2e4 = &diffs[1];
+        assert_eq!(e2e4.found, Some(20));
+        assert_eq!(e2e4.expected, Some(20));
+        assert_eq!(e2e4.found_duplicates, vec![21, 22]);
+        assert!(e2e4.expected_duplicates.is_empty());
+        assert!(e2e4.is_mismatch());
+    }
+
+    #[test]
+    fn lines_up_missing_and_extra_moves() {
+        let found = result(&[("e2e4", 20), ("a2a5", 1)]);
+        let expected = result(&[("e2e4", 20), ("g1f3", 20)]);
+        let diffs = diff(&found, &expected);
+
+        let summary = diffs
+            .iter()
+            .map(|diff| (diff.mv.to_string(), diff.found, diff.expected))
+            .collect::<Vec<_>>();
+
+        assert_eq!(summary, vec![
+            ("a2a5".to_string(), Some(1), None),
+            ("e2e4".to_string(), Some(20), Some(20)),
+            ("g1f3".to_string(), None, Some(20)),
+        ]);
+    }
+}
@@ -0,0 +1,599 @@
+//! Explain, in plain words, why a move in a diff is illegal, or which rule
+//! makes a missed move legal.
+//!
+//! When the backend under test generates a move the reference doesn't know
+//! about (or vice versa), one usually ends up reasoning manually about pins,
+//! checks and castling rights. The helpers in this module do that reasoning
+//! using the reference's board state.
+
+use itertools::Itertools;
+use simbelmyne_chess::bitboard::Bitboard;
+use simbelmyne_chess::board::Board;
+use simbelmyne_chess::constants::RANKS;
+use simbelmyne_chess::movegen::castling::CastleType;
+use simbelmyne_chess::movegen::lookups::BETWEEN;
+use simbelmyne_chess::movegen::moves::Move;
+use simbelmyne_chess::piece::{Color, Piece, PieceType};
+use simbelmyne_chess::square::Square;
+
+use crate::diff::Diff;
+use crate::san::annotate;
+use crate::variant::{castle_type, side_castle_type, Variant};
+
+/// The overall conclusion for a single diff
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// Both backends agree on the move and its count
+    Agree,
+
+    /// Both backends generate the move, but the counts differ further down
+    Deeper,
+
+    /// The backend under test generates a move the reference considers illegal
+    Illegal,
+
+    /// The backend under test generates something that isn't even a
+    /// pseudo-legal move on this board (e.g., `a1a1`, or a move from an empty
+    /// square)
+    Invalid,
+
+    /// The backend under test misses a legal move
+    Missing,
+
+    /// One of the backends reports the same move more than once
+    Duplicate,
+}
+
+impl Verdict {
+    /// A short headline for the verdict
+    pub fn label(self) -> &'static str {
+        match self {
+            Verdict::Agree => "Agree",
+            Verdict::Deeper => "Count mismatch",
+            Verdict::Illegal => "Illegal move",
+            Verdict::Invalid => "Invalid move",
+            Verdict::Missing => "Missing move",
+            Verdict::Duplicate => "Duplicate move",
+        }
+    }
+}
+
+/// A verdict for a diff, along with the reasons that support it
+#[derive(Debug, Clone)]
+pub struct Explanation {
+    pub verdict: Verdict,
+    pub reasons: Vec<String>,
+}
+
+/// Explain a single diff, in the context of the board it was generated on.
+pub fn explain(board: &Board, variant: Variant, diff: &Diff) -> Explanation {
+    if diff.is_duplicate() {
+        return Explanation {
+            verdict: Verdict::Duplicate,
+            reasons: why_duplicate(diff),
+        };
+    }
+
+    match (diff.found, diff.expected) {
+        (Some(_), None) => match why_invalid(board, variant, diff.mv) {
+            Some(reason) => Explanation {
+                verdict: Verdict::Invalid,
+                reasons: vec![reason],
+            },
+
+            None => Explanation {
+                verdict: Verdict::Illegal,
+                reasons: why_illegal(board, variant, diff.mv),
+            },
+        },
+
+        (None, Some(_)) => Explanation {
+            verdict: Verdict::Missing,
+            reasons: why_legal(board, variant, diff.mv),
+        },
+
+        (Some(found), Some(expected)) if found != expected => Explanation {
+            verdict: Verdict::Deeper,
+            reasons: vec![format!(
+                "Both sides generate {}, but disagree on the number of nodes \
+                below it ({found} found, {expected} expected). Descend into \
+                the move to find out where.",
+                diff.mv
+            )],
+        },
+
+        _ => Explanation {
+            verdict: Verdict::Agree,
+            reasons: vec![format!("Both sides agree on {}.", diff.mv)],
+        },
+    }
+}
+
+/// List the counts reported for a move that was generated more than once.
+fn why_duplicate(diff: &Diff) -> Vec<String> {
+    let sides = [
+        ("The backend under test", diff.found, &diff.found_duplicates),
+        ("The reference", diff.expected, &diff.expected_duplicates),
+    ];
+
+    sides
+        .into_iter()
+        .filter(|(_, _, duplicates)| !duplicates.is_empty())
+        .map(|(side, first, duplicates)| {
+            let counts = first.iter().chain(duplicates.iter()).join(", ");
+
+            format!(
+                "{side} reports {} {} times ({counts} nodes), but every move \
+                should only be generated once.",
+                diff.mv,
+                duplicates.len() + 1,
+            )
+        })
+        .collect()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+//
+// Illegal moves
+//
+////////////////////////////////////////////////////////////////////////////////
+
+/// Check whether a move corresponds to a pseudo-legal move on the board at
+/// all, and return the reason if it doesn't.
+///
+/// Attempts to castle (the king moving two squares, or onto its own rook) are
+/// considered pseudo-legal: whether the castle is allowed is up to the rules
+/// that make a move legal.
+pub fn why_invalid(board: &Board, variant: Variant, mv: Move) -> Option<String> {
+    let us = board.current;
+    let src = mv.src();
+    let tgt = mv.tgt();
+
+    let Some(piece) = board.get_at(src) else {
+        return Some(format!("There is no piece on {src}."));
+    };
+
+    if piece.color() != us {
+        return Some(format!(
+            "The {} on {src} belongs to {}, but it is {}'s turn to move.",
+            piece_name(piece), color_name(piece.color()), color_name(us)
+        ));
+    }
+
+    if src == tgt {
+        return Some(format!("The move from {src} to {tgt} doesn't go anywhere."));
+    }
+
+    if is_castle_attempt(board, variant, mv) {
+        return None;
+    }
+
+    if let Some(captured) = board.get_at(tgt) {
+        if captured.color() == us {
+            return Some(format!("{tgt} is occupied by our own {}.", type_name(captured)));
+        }
+
+        if captured.is_king() {
+            return Some(format!("The king on {tgt} can never be captured."));
+        }
+    }
+
+    if piece.is_pawn() && tgt.is_promo_rank(us) && !mv.is_promotion() {
+        return Some(format!("A pawn reaching {tgt} has to promote."));
+    }
+
+    if mv.is_promotion() && !(piece.is_pawn() && tgt.is_promo_rank(us)) {
+        return Some(String::from("Only pawns reaching the last rank can promote."));
+    }
+
+    unreachable(board, piece, src, tgt)
+}
+
+/// The king moving sideways from the square it castles from, either two
+/// squares or onto its own rook.
+pub fn is_castle_attempt(board: &Board, variant: Variant, mv: Move) -> bool {
+    let us = board.current;
+    let src = mv.src();
+    let tgt = mv.tgt();
+    let is_king = board.get_at(src).is_some_and(|piece| piece.is_king());
+    let onto_rook = board.get_at(tgt) == Some(Piece::new(PieceType::Rook, us));
+    let on_castling_square = CastleType::ALL
+        .into_iter()
+        .filter(|ctype| ctype.color() == us)
+        .any(|ctype| variant.castle_squares(ctype).0 == src);
+
+    is_king 
+        && on_castling_square
+        && src.rank() == tgt.rank() 
+        && (src.distance(tgt) == 2 || onto_rook)
+}
+
+/// Find out why the reference doesn't consider a move legal.
+///
+/// The move is only expected to have a source and target square (and a
+/// promotion type), since that is all we get from the backend under test.
+fn why_illegal(board: &Board, variant: Variant, mv: Move) -> Vec<String> {
+    if let Some(reason) = why_invalid(board, variant, mv) {
+        return vec![reason];
+    }
+
+    if is_castle_attempt(board, variant, mv) {
+        return why_no_castle(board, variant, mv.src(), mv.tgt());
+    }
+
+    // The move is at least pseudo-legal, so see what happens to our king when
+    // we play it.
+    let piece = board.get_at(mv.src()).unwrap();
+    why_in_check(board, variant, piece, annotate(board, mv))
+}
+
+/// Check whether the piece can actually get from `src` to `tgt`, and return
+/// the reason if it can't.
+fn unreachable(board: &Board, piece: Piece, src: Square, tgt: Square) -> Option<String> {
+    let blockers = board.all_occupied();
+    let name = type_name(piece);
+
+    let (reachable, unobstructed) = match piece.piece_type() {
+        PieceType::Pawn => return unreachable_for_pawn(board, src, tgt),
+        PieceType::Knight => (src.knight_squares(), src.knight_squares()),
+        PieceType::King => (src.king_squares(), src.king_squares()),
+        PieceType::Bishop => (src.bishop_squares(Bitboard::EMPTY), src.bishop_squares(blockers)),
+        PieceType::Rook => (src.rook_squares(Bitboard::EMPTY), src.rook_squares(blockers)),
+        PieceType::Queen => (src.queen_squares(Bitboard::EMPTY), src.queen_squares(blockers)),
+    };
+
+    if !reachable.contains(tgt) {
+        return Some(format!("A {name} on {src} can't move to {tgt}."));
+    }
+
+    if !unobstructed.contains(tgt) {
+        let blocker = (BETWEEN[src as usize][tgt as usize] & blockers).first();
+        return Some(format!(
+            "The {name} on {src} is blocked by the {} on {blocker}.",
+            piece_name(board.get_at(blocker).unwrap())
+        ));
+    }
+
+    None
+}
+
+/// Pawns are special: they push forward, capture diagonally, can double push
+/// from their starting rank and capture en-passant.
+fn unreachable_for_pawn(board: &Board, src: Square, tgt: Square) -> Option<String> {
+    let us = board.current;
+    let single = src.forward(us);
+    let double = single.and_then(|sq| sq.forward(us));
+    let starting_rank = if us.is_white() { 1 } else { 6 };
+
+    if Some(tgt) == single {
+        return board.get_at(tgt).map(|blocker| format!(
+            "The pawn on {src} is blocked by the {} on {tgt}.",
+            piece_name(blocker)
+        ));
+    }
+
+    if Some(tgt) == double {
+        if src.rank() != starting_rank {
+            return Some(String::from("Pawns can only double push from their starting rank."));
+        }
+
+        let single = single.unwrap();
+
+        return [single, tgt]
+            .into_iter()
+            .find_map(|sq| board.get_at(sq).map(|blocker| format!(
+                "The pawn on {src} is blocked by the {} on {sq}.",
+                piece_name(blocker)
+            )));
+    }
+
+    if src.pawn_attacks(us).contains(tgt) {
+        if board.get_at(tgt).is_some() || board.en_passant == Some(tgt) {
+            return None;
+        }
+
+        return Some(match board.en_passant {
+            Some(ep) => format!(
+                "There is nothing to capture on {tgt}, and the en-passant \
+                square is {ep}."
+            ),
+            None => format!(
+                "There is nothing to capture on {tgt}, and there is no \
+                en-passant square."
+            ),
+        });
+    }
+
+    Some(format!("A pawn on {src} can't move to {tgt}."))
+}
+
+/// The king is trying to move two squares, or onto its own rook: explain what
+/// is stopping it from castling.
+fn why_no_castle(board: &Board, variant: Variant, src: Square, tgt: Square) -> Vec<String> {
+    let us = board.current;
+    let ctype = side_castle_type(us, tgt.file() > src.file());
+
+    let side = castle_name(ctype);
+
+    if !board.castling_rights.is_available(ctype) {
+        return vec![format!(
+            "{} has no {side} castling rights left (castling rights: {}).",
+            capitalize(color_name(us)),
+            variant.castling_rights(board)
+        )];
+    }
+
+    let (king_sq, rook_sq) = variant.castle_squares(ctype);
+    let king_tgt = ctype.king_move().tgt();
+    let rook_tgt = ctype.rook_move().tgt();
+
+    if src != king_sq || (tgt != king_tgt && tgt != rook_sq) {
+        return vec![format!(
+            "Castling {side} takes the king from {king_sq} to {king_tgt}, and \
+            can also be written as the king capturing the rook on {rook_sq}."
+        )];
+    }
+
+    if board.get_at(rook_sq) != Some(Piece::new(PieceType::Rook, us)) {
+        return vec![format!("There is no rook on {rook_sq} to castle with.")];
+    }
+
+    // Every square the king and rook pass through or land on has to be empty,
+    // apart from the king and rook themselves.
+    let path = BETWEEN[king_sq as usize][king_tgt as usize]
+        | Bitboard::from(king_tgt)
+        | BETWEEN[rook_sq as usize][rook_tgt as usize]
+        | Bitboard::from(rook_tgt);
+
+    let blockers = path
+        & board.all_occupied()
+        & !Bitboard::from(king_sq)
+        & !Bitboard::from(rook_sq);
+
+    if !blockers.is_empty() {
+        return vec![format!(
+            "The squares the king and the rook move through are occupied ({}).",
+            blockers.map(|sq| sq.to_string()).join(", ")
+        )];
+    }
+
+    let checkers = attackers_of(board, src, !us);
+
+    if !checkers.is_empty() {
+        return vec![format!(
+            "The king can't castle out of check (attacked by {}).",
+            describe_all(board, checkers)
+        )];
+    }
+
+    let path = BETWEEN[src as usize][king_tgt as usize] | Bitboard::from(king_tgt);
+
+    let reasons = path
+        .filter_map(|sq| {
+            let attackers = attackers_of(board, sq, !us);
+
+            if attackers.is_empty() {
+                return None;
+            }
+
+            let verb = if sq == king_tgt { "lands on" } else { "passes through" };
+
+            Some(format!(
+                "The king {verb} {sq}, which is attacked by {}.",
+                describe_all(board, attackers)
+            ))
+        })
+        .collect_vec();
+
+    if reasons.is_empty() {
+        vec![String::from("Castling looks legal, but the reference doesn't generate it.")]
+    } else {
+        reasons
+    }
+}
+
+/// The move is pseudo-legal: play it, and describe what's attacking our king
+/// afterwards.
+fn why_in_check(board: &Board, variant: Variant, piece: Piece, mv: Move) -> Vec<String> {
+    let us = board.current;
+    let src = mv.src();
+    let new_board = variant.play_move(board, mv);
+    let king_sq = new_board.kings(us).first();
+    let old_checkers = board.get_checkers(us);
+    let new_checkers = attackers_of(&new_board, king_sq, !us);
+
+    if new_checkers.is_empty() {
+        return vec![String::from(
+            "The move looks legal, but the reference doesn't generate it."
+        )];
+    }
+
+    let attackers = describe_all(&new_board, new_checkers);
+
+    if piece.is_king() {
+        return vec![format!("The king would move into check from {attackers}.")];
+    }
+
+    if old_checkers.count() > 1 {
+        return vec![format!(
+            "The king is in double check from {}, so only king moves are \
+            allowed.",
+            describe_all(board, old_checkers)
+        )];
+    }
+
+    if !old_checkers.is_empty() {
+        return vec![format!(
+            "The king is in check from {}, and this move doesn't resolve it.",
+            describe_all(board, old_checkers)
+        )];
+    }
+
+    if mv.is_en_passant() && new_checkers.overlap(RANKS[king_sq.rank()]) == new_checkers {
+        return vec![format!(
+            "Capturing en-passant removes both pawns from the rank, exposing \
+            the king on {king_sq} to {attackers}."
+        )];
+    }
+
+    if board.get_pinrays(us).contains(src) {
+        return vec![format!(
+            "The {} on {src} is pinned to the king by {attackers}.",
+            type_name(piece)
+        )];
+    }
+
+    vec![format!("The move leaves the king on {king_sq} in check from {attackers}.")]
+}
+
+////////////////////////////////////////////////////////////////////////////////
+//
+// Missing moves
+//
+////////////////////////////////////////////////////////////////////////////////
+
+/// Describe the rules that make a (legal) move legal.
+fn why_legal(board: &Board, variant: Variant, mv: Move) -> Vec<String> {
+    let us = board.current;
+    let src = mv.src();
+    let tgt = mv.tgt();
+    let Some(piece) = board.get_at(src) else {
+        return vec![format!("There is no piece on {src}.")];
+    };
+
+    let mut reasons = Vec::new();
+
+    if mv.is_castle() {
+        let ctype = castle_type(us, mv);
+        let (_, rook_sq) = variant.castle_squares(ctype);
+
+        reasons.push(format!(
+            "{} castles {} with the rook on {rook_sq}: the castling right is \
+            still available ({}), the squares the king and the rook move \
+            through are empty, and the king doesn't start on, pass through or \
+            land on an attacked square.",
+            capitalize(color_name(us)),
+            castle_name(ctype),
+            variant.castling_rights(board),
+        ));
+    } else if mv.is_en_passant() {
+        reasons.push(format!(
+            "{tgt} is the en-passant square, so the pawn on {src} can capture \
+            the pawn on {} en-passant.",
+            mv.get_capture_sq()
+        ));
+        reasons.push(String::from(
+            "Removing both pawns from the rank doesn't expose the king."
+        ));
+    } else if mv.is_double_push() {
+        reasons.push(format!(
+            "The pawn on {src} is on its starting rank, and both squares in \
+            front of it are empty, so it can double push to {tgt}."
+        ));
+    } else if let Some(captured) = board.get_at(tgt) {
+        reasons.push(format!(
+            "The {} on {src} captures the {} on {tgt}.",
+            type_name(piece), piece_name(captured)
+        ));
+    } else {
+        reasons.push(format!("The {} on {src} moves to {tgt}.", type_name(piece)));
+    }
+
+    if let Some(ptype) = mv.get_promo_type() {
+        reasons.push(format!(
+            "The pawn promotes to a {}. All four promotions, including \
+            underpromotions, are legal moves.",
+            ptype_name(ptype)
+        ));
+    }
+
+    let checkers = board.get_checkers(us);
+
+    if !checkers.is_empty() {
+        let checkers_desc = describe_all(board, checkers);
+
+        reasons.push(if piece.is_king() {
+            format!("The king moves out of check from {checkers_desc}.")
+        } else if checkers.contains(mv.get_capture_sq()) {
+            format!("The move captures the checking {checkers_desc}.")
+        } else {
+            format!("The move blocks the check from {checkers_desc}.")
+        });
+    } else if piece.is_king() && !mv.is_castle() {
+        reasons.push(format!("{tgt} is not attacked by the opponent."));
+    }
+
+    if !piece.is_king() && board.get_pinrays(us).contains(src) {
+        reasons.push(format!(
+            "The {} on {src} is pinned, but stays on the pin ray.",
+            type_name(piece)
+        ));
+    }
+
+    reasons
+}
+
+////////////////////////////////////////////////////////////////////////////////
+//
+// Helpers
+//
+////////////////////////////////////////////////////////////////////////////////
+
+/// All the pieces of a given side attacking a square, kings included
+fn attackers_of(board: &Board, square: Square, side: Color) -> Bitboard {
+    let attackers = board.attackers(square, board.all_occupied())
+        | square.king_squares() & board.kings(side);
+
+    attackers & board.occupied_by(side)
+}
+
+/// Describe every piece in a bitboard, e.g., "the black rook on a8 and the
+/// black bishop on b7"
+fn describe_all(board: &Board, squares: Bitboard) -> String {
+    squares
+        .map(|sq| format!("the {} on {sq}", piece_name(board.get_at(sq).unwrap())))
+        .join(" and ")
+}
+
+fn piece_name(piece: Piece) -> String {
+    format!("{} {}", color_name(piece.color()), type_name(piece))
+}
+
+fn type_name(piece: Piece) -> &'static str {
+    ptype_name(piece.piece_type())
+}
+
+/// The piece type in words, e.g., `knight`
+pub fn ptype_name(ptype: PieceType) -> &'static str {
+    match ptype {
+        PieceType::Pawn => "pawn",
+        PieceType::Knight => "knight",
+        PieceType::Bishop => "bishop",
+        PieceType::Rook => "rook",
+        PieceType::Queen => "queen",
+        PieceType::King => "king",
+    }
+}
+
+fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::White => "white",
+        Color::Black => "black",
+    }
+}
+
+fn castle_name(ctype: CastleType) -> &'static str {
+    match ctype {
+        CastleType::WK | CastleType::BK => "kingside",
+        CastleType::WQ | CastleType::BQ => "queenside",
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,604 @@
+//! The table of key bindings. Both `handle_event` and the help modal read from
+//! it, so the help always lists the keys that actually do something.
+//!
+//! The bindings start out from one of the presets, and individual actions can
+//! be rebound in the config file:
+//!
+//! ```toml
+//! keymap = "emacs"
+//!
+//! [keys]
+//! quit = ["q", "ctrl-c"]
+//! next_mismatch = "tab"
+//! ```
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use clap::ValueEnum;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+use crate::clipboard::Clip;
+use crate::components::board_view::Overlay;
+use crate::tui::Message;
+
+/// The part of the app a binding belongs to, used to group the help
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Group {
+    Navigation,
+    Moves,
+    Board,
+    Session,
+    Share,
+    Search,
+    General,
+}
+
+impl Group {
+    pub const ALL: [Group; 7] = [
+        Group::Navigation,
+        Group::Moves,
+        Group::Board,
+        Group::Session,
+        Group::Share,
+        Group::Search,
+        Group::General,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Group::Navigation => "Navigation",
+            Group::Moves => "Move table",
+            Group::Board => "Board",
+            Group::Session => "Session",
+            Group::Share => "Sharing",
+            Group::Search => "While searching",
+            Group::General => "General",
+        }
+    }
+}
+
+impl Message {
+    /// Where the message shows up in the help
+    pub fn group(self) -> Group {
+        match self {
+            Message::Up
+            | Message::Down
+            | Message::PageUp
+            | Message::PageDown
+            | Message::First
+            | Message::Last
+            | Message::Select
+            | Message::Play(_)
+            | Message::Click(_)
+            | Message::Back
+            | Message::NextMismatch => Group::Navigation,
+
+            Message::StartSearch
+            | Message::ToggleMismatchesOnly
+            | Message::CycleSort => Group::Moves,
+
+            Message::Toggle(_)
+            | Message::Flip
+            | Message::ToggleOrientation => Group::Board,
+
+            Message::SaveSession
+            | Message::EditNotes
+            | Message::AddBookmark
+            | Message::ToggleBookmarks
+            | Message::DeleteBookmark
+            | Message::ExportBookmarks => Group::Session,
+
+            Message::Copy(_) | Message::ExportReport => Group::Share,
+
+            Message::Search(_)
+            | Message::ConfirmSearch
+            | Message::CancelSearch => Group::Search,
+
+            Message::Prompt(_)
+            | Message::ConfirmPrompt
+            | Message::CancelPrompt
+            | Message::ToggleHelp
+            | Message::Quit => Group::General,
+        }
+    }
+
+    /// What the message does, as listed in the help
+    pub fn description(self) -> &'static str {
+        match self {
+            Message::Up => "Select the previous move",
+            Message::Down => "Select the next move",
+            Message::PageUp => "Move the selection up a page",
+            Message::PageDown => "Move the selection down a page",
+            Message::First => "Select the first move",
+            Message::Last => "Select the last move",
+            Message::Select => "Play the selected move",
+            Message::Play(_) => "Play a move",
+            Message::Click(_) => "Select or play the clicked move",
+            Message::Back => "Go back to the previous position",
+            Message::NextMismatch => "Jump to the next mismatching move",
+            Message::StartSearch => "Search the moves",
+            Message::ToggleMismatchesOnly => "Only show mismatching moves",
+            Message::CycleSort => "Cycle through the sort orders",
+            Message::Toggle(Overlay::Attacked) => "Show attacked squares",
+            Message::Toggle(Overlay::Pins) => "Show pinned pieces and pin rays",
+            Message::Toggle(Overlay::Checkers) => "Show checking pieces",
+            Message::Toggle(Overlay::EnPassant) => "Show the en-passant square",
+            Message::Toggle(Overlay::Castling) => "Show the kings and rooks that can castle",
+            Message::Flip => "Flip the board",
+            Message::ToggleOrientation => "Orient the board towards the side to move",
+            Message::Search(_) => "Edit the search query",
+            Message::ConfirmSearch => "Keep the search query",
+            Message::CancelSearch => "Clear the search query",
+            Message::SaveSession => "Save the session, to pick it back up with --session",
+            Message::EditNotes => "Edit the notes saved with the session",
+            Message::AddBookmark => "Bookmark the current position, with a note",
+            Message::ToggleBookmarks => "Show or hide the bookmarks, to jump to one",
+            Message::DeleteBookmark => "Delete the selected bookmark",
+            Message::ExportBookmarks => "Export the bookmarks as an EPD file",
+            Message::Copy(Clip::Fen) => "Copy the FEN",
+            Message::Copy(Clip::UciPosition) => "Copy the UCI command to set up the position",
+            Message::Copy(Clip::Table) => "Copy the move table",
+            Message::Copy(Clip::MarkdownTable) => "Copy the move table as Markdown",
+            Message::ExportReport => "Write a bug report for the position as Markdown",
+            Message::Prompt(_) => "Edit the text in the prompt",
+            Message::ConfirmPrompt => "Confirm the text in the prompt",
+            Message::CancelPrompt => "Cancel the prompt",
+            Message::ToggleHelp => "Show or hide this help",
+            Message::Quit => "Quit (clears the search query first, if any)",
+        }
+    }
+}
+
+/// A key, along with the Ctrl and Alt modifiers held down with it.
+///
+/// In the config file, keys are written as their character (`"j"`, `"?"`) or
+/// name (`"enter"`, `"pageup"`, `"f1"`), optionally prefixed with `ctrl-` or
+/// `alt-` (`"ctrl-n"`, `"alt-<"`).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Key {
+    pub const fn new(code: KeyCode) -> Self {
+        Self { code, modifiers: KeyModifiers::NONE }
+    }
+
+    pub const fn ctrl(code: KeyCode) -> Self {
+        Self { code, modifiers: KeyModifiers::CONTROL }
+    }
+
+    pub const fn alt(code: KeyCode) -> Self {
+        Self { code, modifiers: KeyModifiers::ALT }
+    }
+
+    /// Whether typing this key would insert a character into a text input
+    fn is_text(self) -> bool {
+        matches!(self.code, KeyCode::Char(_)) && self.modifiers.is_empty()
+    }
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        // Shift is already accounted for in the character itself
+        let mut modifiers = event.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT);
+
+        if !matches!(event.code, KeyCode::Char(_)) {
+            modifiers |= event.modifiers & KeyModifiers::SHIFT;
+        }
+
+        Self { code: event.code, modifiers }
+    }
+}
+
+impl FromStr for Key {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut rest = s;
+        let mut modifiers = KeyModifiers::NONE;
+
+        // Peel off the modifiers, but leave a lone `-` as the key itself
+        while let Some((prefix, key)) = rest.split_once('-').filter(|(_, key)| !key.is_empty()) {
+            modifiers |= match prefix.to_ascii_lowercase().as_str() {
+                "ctrl" | "c" => KeyModifiers::CONTROL,
+                "alt" | "m" => KeyModifiers::ALT,
+                "shift" | "s" => KeyModifiers::SHIFT,
+                _ => break,
+            };
+
+            rest = key;
+        }
+
+        let mut chars = rest.chars();
+
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_ascii_lowercase().as_str() {
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "backspace" => KeyCode::Backspace,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "space" => KeyCode::Char(' '),
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "pageup" | "pgup" => KeyCode::PageUp,
+                "pagedown" | "pgdn" => KeyCode::PageDown,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "insert" => KeyCode::Insert,
+                "delete" | "del" => KeyCode::Delete,
+                name => name
+                    .strip_prefix('f')
+                    .and_then(|n| n.parse().ok())
+                    .map(KeyCode::F)
+                    .ok_or(anyhow!("Unknown key '{s}'"))?,
+            },
+        };
+
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl TryFrom<String> for Key {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> anyhow::Result<Self> {
+        value.parse()
+    }
+}
+
+impl Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl-")?;
+        }
+
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt-")?;
+        }
+
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift-")?;
+        }
+
+        let name = match self.code {
+            KeyCode::Char(' ') => String::from("Space"),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::F(n) => format!("F{n}"),
+            KeyCode::Up => String::from("↑"),
+            KeyCode::Down => String::from("↓"),
+            KeyCode::Left => String::from("←"),
+            KeyCode::Right => String::from("→"),
+            KeyCode::PageUp => String::from("PgUp"),
+            KeyCode::PageDown => String::from("PgDn"),
+            other => format!("{other:?}"),
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+/// The built-in sets of key bindings. All of them can be navigated with the
+/// arrow keys, PageUp/PageDown and Home/End.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeymapPreset {
+    /// `h`/`j`/`k`/`l` on top of the arrow keys
+    #[default]
+    Default,
+
+    /// The defaults, plus `g`/`G` for the first and last move, and
+    /// `Ctrl-u`/`Ctrl-d` to page up and down
+    Vim,
+
+    /// `Ctrl-p`/`Ctrl-n`/`Ctrl-f`/`Ctrl-b` to navigate, `Alt-<`/`Alt->` for
+    /// the first and last move, `Alt-v`/`Ctrl-v` to page, `Ctrl-s` to search
+    /// and `Ctrl-g` to cancel a search
+    Emacs,
+}
+
+/// One or more keys to bind to an action
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Keys {
+    One(Key),
+    Many(Vec<Key>),
+}
+
+impl Keys {
+    fn to_vec(&self) -> Vec<Key> {
+        match self {
+            Keys::One(key) => vec![*key],
+            Keys::Many(keys) => keys.clone(),
+        }
+    }
+}
+
+/// Keys to bind to individual actions, replacing the keys the preset binds to
+/// them.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeyOverrides {
+    pub up: Option<Keys>,
+    pub down: Option<Keys>,
+    pub page_up: Option<Keys>,
+    pub page_down: Option<Keys>,
+    pub first: Option<Keys>,
+    pub last: Option<Keys>,
+    pub select: Option<Keys>,
+    pub back: Option<Keys>,
+    pub next_mismatch: Option<Keys>,
+    pub search: Option<Keys>,
+    pub mismatches_only: Option<Keys>,
+    pub sort: Option<Keys>,
+    pub attacked: Option<Keys>,
+    pub pins: Option<Keys>,
+    pub checkers: Option<Keys>,
+    pub en_passant: Option<Keys>,
+    pub castling: Option<Keys>,
+    pub flip: Option<Keys>,
+    pub orient: Option<Keys>,
+    pub save_session: Option<Keys>,
+    pub notes: Option<Keys>,
+    pub bookmark: Option<Keys>,
+    pub bookmarks: Option<Keys>,
+    pub delete_bookmark: Option<Keys>,
+    pub export_bookmarks: Option<Keys>,
+    pub copy_fen: Option<Keys>,
+    pub copy_position: Option<Keys>,
+    pub copy_table: Option<Keys>,
+    pub copy_markdown: Option<Keys>,
+    pub report: Option<Keys>,
+    pub help: Option<Keys>,
+    pub quit: Option<Keys>,
+    pub confirm_search: Option<Keys>,
+    pub cancel_search: Option<Keys>,
+}
+
+/// Maps keys onto the messages they trigger
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    /// The bindings while browsing the moves
+    normal: Vec<(Key, Message)>,
+
+    /// The bindings while typing a search query. Any other key edits the query.
+    search: Vec<(Key, Message)>,
+}
+
+impl Keymap {
+    pub fn new(preset: KeymapPreset) -> Self {
+        use KeyCode::*;
+
+        let navigation = match preset {
+            KeymapPreset::Default => vec![
+                (Key::new(Char('k')), Message::Up),
+                (Key::new(Char('j')), Message::Down),
+                (Key::new(Char('l')), Message::Select),
+                (Key::new(Char('h')), Message::Back),
+            ],
+
+            KeymapPreset::Vim => vec![
+                (Key::new(Char('k')), Message::Up),
+                (Key::new(Char('j')), Message::Down),
+                (Key::ctrl(Char('u')), Message::PageUp),
+                (Key::ctrl(Char('d')), Message::PageDown),
+                (Key::new(Char('g')), Message::First),
+                (Key::new(Char('G')), Message::Last),
+                (Key::new(Char('l')), Message::Select),
+                (Key::new(Char('h')), Message::Back),
+            ],
+
+            KeymapPreset::Emacs => vec![
+                (Key::ctrl(Char('p')), Message::Up),
+                (Key::ctrl(Char('n')), Message::Down),
+                (Key::alt(Char('v')), Message::PageUp),
+                (Key::ctrl(Char('v')), Message::PageDown),
+                (Key::alt(Char('<')), Message::First),
+                (Key::alt(Char('>')), Message::Last),
+                (Key::ctrl(Char('f')), Message::Select),
+                (Key::ctrl(Char('b')), Message::Back),
+                (Key::ctrl(Char('s')), Message::StartSearch),
+            ],
+        };
+
+        let common = vec![
+            (Key::new(Up), Message::Up),
+            (Key::new(Down), Message::Down),
+            (Key::new(PageUp), Message::PageUp),
+            (Key::new(PageDown), Message::PageDown),
+            (Key::new(Home), Message::First),
+            (Key::new(End), Message::Last),
+            (Key::new(Enter), Message::Select),
+            (Key::new(Right), Message::Select),
+            (Key::new(Left), Message::Back),
+            (Key::new(Backspace), Message::Back),
+            (Key::new(Char('n')), Message::NextMismatch),
+            (Key::new(Char('/')), Message::StartSearch),
+            (Key::new(Char('m')), Message::ToggleMismatchesOnly),
+            (Key::new(Char('s')), Message::CycleSort),
+            (Key::new(Char('a')), Message::Toggle(Overlay::Attacked)),
+            (Key::new(Char('p')), Message::Toggle(Overlay::Pins)),
+            (Key::new(Char('c')), Message::Toggle(Overlay::Checkers)),
+            (Key::new(Char('e')), Message::Toggle(Overlay::EnPassant)),
+            (Key::new(Char('r')), Message::Toggle(Overlay::Castling)),
+            (Key::new(Char('f')), Message::Flip),
+            (Key::new(Char('o')), Message::ToggleOrientation),
+            (Key::new(Char('w')), Message::SaveSession),
+            (Key::new(Char('N')), Message::EditNotes),
+            (Key::new(Char('b')), Message::AddBookmark),
+            (Key::new(Char('B')), Message::ToggleBookmarks),
+            (Key::new(Char('d')), Message::DeleteBookmark),
+            (Key::new(Char('E')), Message::ExportBookmarks),
+            (Key::new(Char('y')), Message::Copy(Clip::Fen)),
+            (Key::new(Char('u')), Message::Copy(Clip::UciPosition)),
+            (Key::new(Char('t')), Message::Copy(Clip::Table)),
+            (Key::new(Char('T')), Message::Copy(Clip::MarkdownTable)),
+            (Key::new(Char('R')), Message::ExportReport),
+            (Key::new(Char('?')), Message::ToggleHelp),
+            (Key::new(Char('q')), Message::Quit),
+            (Key::new(Esc), Message::Quit),
+        ];
+
+        let mut search = vec![
+            (Key::new(Enter), Message::ConfirmSearch),
+            (Key::new(Esc), Message::CancelSearch),
+            (Key::new(Up), Message::Up),
+            (Key::new(Down), Message::Down),
+        ];
+
+        if preset == KeymapPreset::Emacs {
+            search.extend([
+                (Key::ctrl(Char('g')), Message::CancelSearch),
+                (Key::ctrl(Char('p')), Message::Up),
+                (Key::ctrl(Char('n')), Message::Down),
+            ]);
+        }
+
+        Self {
+            normal: navigation.into_iter().chain(common).collect(),
+            search,
+        }
+    }
+
+    /// Rebind the actions set in the config file. The new keys replace the
+    /// keys the action was bound to, and are taken away from any other action
+    /// they were bound to.
+    ///
+    /// Rebinding `up` or `down` also applies while searching, but only for
+    /// keys that wouldn't otherwise be typed into the query.
+    pub fn with_overrides(mut self, overrides: &KeyOverrides) -> Self {
+        let fields = [
+            (&overrides.up, Message::Up),
+            (&overrides.down, Message::Down),
+            (&overrides.page_up, Message::PageUp),
+            (&overrides.page_down, Message::PageDown),
+            (&overrides.first, Message::First),
+            (&overrides.last, Message::Last),
+            (&overrides.select, Message::Select),
+            (&overrides.back, Message::Back),
+            (&overrides.next_mismatch, Message::NextMismatch),
+            (&overrides.search, Message::StartSearch),
+            (&overrides.mismatches_only, Message::ToggleMismatchesOnly),
+            (&overrides.sort, Message::CycleSort),
+            (&overrides.attacked, Message::Toggle(Overlay::Attacked)),
+            (&overrides.pins, Message::Toggle(Overlay::Pins)),
+            (&overrides.checkers, Message::Toggle(Overlay::Checkers)),
+            (&overrides.en_passant, Message::Toggle(Overlay::EnPassant)),
+            (&overrides.castling, Message::Toggle(Overlay::Castling)),
+            (&overrides.flip, Message::Flip),
+            (&overrides.orient, Message::ToggleOrientation),
+            (&overrides.save_session, Message::SaveSession),
+            (&overrides.notes, Message::EditNotes),
+            (&overrides.bookmark, Message::AddBookmark),
+            (&overrides.bookmarks, Message::ToggleBookmarks),
+            (&overrides.delete_bookmark, Message::DeleteBookmark),
+            (&overrides.export_bookmarks, Message::ExportBookmarks),
+            (&overrides.copy_fen, Message::Copy(Clip::Fen)),
+            (&overrides.copy_position, Message::Copy(Clip::UciPosition)),
+            (&overrides.copy_table, Message::Copy(Clip::Table)),
+            (&overrides.copy_markdown, Message::Copy(Clip::MarkdownTable)),
+            (&overrides.report, Message::ExportReport),
+            (&overrides.help, Message::ToggleHelp),
+            (&overrides.quit, Message::Quit),
+        ];
+
+        for (keys, message) in fields {
+            if let Some(keys) = keys {
+                rebind(&mut self.normal, message, keys.to_vec());
+
+                if matches!(message, Message::Up | Message::Down) {
+                    let keys = keys.to_vec().into_iter().filter(|key| !key.is_text()).collect();
+                    rebind(&mut self.search, message, keys);
+                }
+            }
+        }
+
+        let search_fields = [
+            (&overrides.confirm_search, Message::ConfirmSearch),
+            (&overrides.cancel_search, Message::CancelSearch),
+        ];
+
+        for (keys, message) in search_fields {
+            if let Some(keys) = keys {
+                rebind(&mut self.search, message, keys.to_vec());
+            }
+        }
+
+        self
+    }
+
+    /// The message bound to a key while browsing the moves
+    pub fn get(&self, key: Key) -> Option<Message> {
+        lookup(&self.normal, key)
+    }
+
+    /// The message bound to a key while typing a search query
+    pub fn get_search(&self, key: Key) -> Option<Message> {
+        lookup(&self.search, key)
+    }
+
+    /// All the keys bound to a message while browsing the moves
+    pub fn keys_for(&self, message: Message) -> Vec<Key> {
+        self.normal
+            .iter()
+            .filter(|(_, bound)| *bound == message)
+            .map(|&(key, _)| key)
+            .collect()
+    }
+
+    /// All the bindings in a group, with the keys bound to the same message
+    /// collected together, in the order they were bound.
+    pub fn group(&self, group: Group) -> Vec<(Vec<Key>, Message)> {
+        let bindings = if group == Group::Search { &self.search } else { &self.normal };
+        let mut entries: Vec<(Vec<Key>, Message)> = Vec::new();
+
+        for &(key, message) in bindings {
+            if group != Group::Search && message.group() != group {
+                continue;
+            }
+
+            match entries.iter_mut().find(|(_, other)| *other == message) {
+                Some((keys, _)) => keys.push(key),
+                None => entries.push((vec![key], message)),
+            }
+        }
+
+        entries
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new(KeymapPreset::default())
+    }
+}
+
+fn lookup(bindings: &[(Key, Message)], key: Key) -> Option<Message> {
+    bindings
+        .iter()
+        .find(|(bound, _)| *bound == key)
+        .map(|&(_, message)| message)
+}
+
+/// Bind a message to a new set of keys, dropping its old keys, as well as any
+/// other bindings for the new keys.
+fn rebind(bindings: &mut Vec<(Key, Message)>, message: Message, keys: Vec<Key>) {
+    let position = bindings
+        .iter()
+        .position(|&(_, bound)| bound == message)
+        .unwrap_or(bindings.len());
+
+    bindings.retain(|(key, bound)| *bound != message && !keys.contains(key));
+
+    let position = position.min(bindings.len());
+    bindings.splice(position..position, keys.into_iter().map(|key| (key, message)));
+}
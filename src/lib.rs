@@ -0,0 +1,25 @@
+//! Pretty-perft: a library for comparing split perft results between a chess
+//! engine and a reference move generator.
+//!
+//! The library exposes
+//! - the `Perft` trait and the backends that implement it (a UCI `Engine`, a
+//!   stand-alone `Executable`, and the `Simbelmyne` reference implementation),
+//! - the parser for split perft ("divide") output,
+//! - the diffing logic that lines up two split perft results, and
+//! - the auto-descent logic that walks down the tree along mismatching moves,
+//!   and the search for minimal reproducers built on top of it, and
+//! - explanations of why a move is illegal, or why a missed move is legal.
+//!
+//! The TUI in the `pretty-perft` binary is a thin consumer of this API.
+
+pub mod backends;
+pub mod diff;
+pub mod explain;
+pub mod perft;
+pub mod reproducer;
+pub mod san;
+pub mod variant;
+
+pub use backends::{Perft, PerftResult};
+pub use diff::{compare, compare_results, descend, diff, Descent, Diff};
+pub use reproducer::{find_reproducer, Reproducer};
@@ -1,32 +1,433 @@
-use clap::Parser;
+use clap::builder::RangedU64ValueParser;
+use clap::{Parser, Subcommand};
 use std::path::PathBuf;
+use std::time::Instant;
 
+use pretty_perft::backends::{Engine, Executable, Perft, Simbelmyne};
+use pretty_perft::find_reproducer;
+use pretty_perft::variant::Variant;
+use simbelmyne_chess::board::Board;
+
+use bench::{read_positions, Bench, BenchArgs, BenchPosition, BenchResult};
+use config::{FileConfig, Profile};
+use keymap::{Keymap, KeymapPreset};
+use session::Session;
+use theme::{PieceStyle, Theme, ThemeName};
+
+mod bench;
+mod clipboard;
 mod components;
-mod backends;
-mod perft;
+mod config;
+mod keymap;
+mod report;
+mod session;
+mod theme;
 mod tui;
 
 #[derive(Parser)]
 #[command(author = "Sam Roelants", version = "0.1", about = "A simple perft tool.", long_about = None)]
 struct Config {
-    /// The desired search depth, in ply (half-turns)
-    #[arg(short, long, default_value = "5")]
-    depth: usize,
+    /// The desired search depth, in ply (half-turns), counted from the 
+    /// starting position: a split perft at depth 5 lists the moves from the
+    /// starting position, along with the perft(4) below each of them, for a
+    /// total of perft(5) [default: 5]
+    #[arg(short, long, global = true, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    depth: Option<usize>,
 
-    #[arg(
-        short,
-        long,
-        default_value = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
-    )]
-    fen: String,
+    /// The starting position [default: the standard starting position]
+    #[arg(short, long, global = true)]
+    fen: Option<String>,
 
-    #[arg(short, long)]
+    /// Path to a UCI engine to test
+    #[arg(short, long, global = true)]
     engine: Option<PathBuf>,
 
-    #[arg(short, long)]
+    /// Path to a stand-alone perft script to test
+    #[arg(short, long, global = true)]
     command: Option<PathBuf>,
+
+    /// The engine profile from the config file to test
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
+    /// Play Chess960 (or Double Fischer Random). Castling rights in the FEN 
+    /// can be given as Shredder-FEN or X-FEN, and castles are written as the 
+    /// king capturing its own rook.
+    #[arg(long, global = true, overrides_with = "no_chess960")]
+    chess960: bool,
+
+    /// Play standard chess, even if the session or config file says Chess960
+    #[arg(long, global = true)]
+    no_chess960: bool,
+
+    /// Always draw the board from the perspective of the side to move
+    #[arg(short, long, overrides_with = "no_orient_to_move")]
+    orient_to_move: bool,
+
+    /// Draw the board from White's perspective, even if the config file says
+    /// to orient it to the side to move
+    #[arg(long)]
+    no_orient_to_move: bool,
+
+    /// The color theme to use
+    #[arg(long, value_enum)]
+    theme: Option<ThemeName>,
+
+    /// How to draw the pieces on the board
+    #[arg(long, value_enum)]
+    pieces: Option<PieceStyle>,
+
+    /// The set of key bindings to start from
+    #[arg(long, value_enum)]
+    keymap: Option<KeymapPreset>,
+
+    /// Pick up the session saved in this file, if it exists, and save the 
+    /// session to it [default: pretty-perft-session.toml]
+    #[arg(long)]
+    session: Option<PathBuf>,
+
+    /// Path to a config file to use instead of the default ones
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Don't start the TUI, but search for the simplest position where the 
+    /// move lists differ, and print it
+    #[arg(short, long)]
+    reproduce: bool,
+
+    /// The maximum number of positions to compare when looking for alternative
+    /// reproducers [default: 50]
+    #[arg(long)]
+    max_positions: Option<usize>,
+
+    /// The number of threads the reference move generator runs on, in a 
+    /// thread pool of its own. With 1, it doesn't start any threads at all
+    /// [default: one for every core]
+    #[arg(long, global = true)]
+    threads: Option<usize>,
+
+    #[command(subcommand)]
+    mode: Option<Mode>,
+}
+
+#[derive(Subcommand)]
+enum Mode {
+    /// Don't start the TUI, but time perft on a set of positions, a number of
+    /// times, and report how long it took
+    Bench(BenchArgs),
+}
+
+const DEFAULT_DEPTH: usize = 5;
+const DEFAULT_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+const DEFAULT_MAX_POSITIONS: usize = 50;
+
+impl Config {
+    /// The search depth, from the command line, the session or the config 
+    /// file
+    fn depth(&self, file_config: &FileConfig, session: Option<&Session>) -> anyhow::Result<usize> {
+        let depth = self.depth
+            .or(session.map(|session| session.depth))
+            .or(file_config.depth)
+            .unwrap_or(DEFAULT_DEPTH);
+
+        if depth == 0 {
+            anyhow::bail!("The search depth should be at least 1");
+        }
+
+        Ok(depth)
+    }
+
+    /// The number of positions to try when looking for a reproducer
+    fn max_positions(&self, file_config: &FileConfig) -> usize {
+        self.max_positions.or(file_config.max_positions).unwrap_or(DEFAULT_MAX_POSITIONS)
+    }
+
+    /// Whether to draw the board from the perspective of the side to move
+    fn orient_to_move(&self, file_config: &FileConfig) -> bool {
+        flag(self.orient_to_move, self.no_orient_to_move)
+            .or(file_config.orient_to_move)
+            .unwrap_or(false)
+    }
+
+    /// Whether to play Chess960, from the command line, the session or the
+    /// config file
+    fn chess960(&self, file_config: &FileConfig, session: Option<&Session>) -> bool {
+        flag(self.chess960, self.no_chess960).unwrap_or_else(|| {
+            session.is_some_and(|session| session.chess960)
+                || file_config.chess960.unwrap_or(false)
+        })
+    }
+
+    /// Parse the starting position, along with the variant it's played in
+    fn position(
+        &self, 
+        file_config: &FileConfig, 
+        session: Option<&Session>
+    ) -> anyhow::Result<(Board, Variant)> {
+        let fen = self.fen.as_deref()
+            .or(session.map(|session| session.fen.as_str()))
+            .or(file_config.fen.as_deref())
+            .unwrap_or(DEFAULT_FEN);
+
+        Variant::parse_fen(fen, self.chess960(file_config, session))
+    }
+
+    /// The engine or script to test, and how to talk to it. 
+    ///
+    /// A profile picked on the command line takes precedence over the backend
+    /// of the session, which takes precedence over the profile picked in the
+    /// config file. An engine or command on the command line replaces the one
+    /// in the profile, and the one set at the top of the config file is only 
+    /// used if the profile doesn't have one.
+    fn profile(&self, file_config: &FileConfig, session: Option<&Session>) -> anyhow::Result<Profile> {
+        let mut profile = match (&self.profile, session) {
+            (Some(name), _) => file_config.profile(name)?.clone(),
+            (None, Some(session)) => session.backend.clone(),
+            (None, None) => match &file_config.profile {
+                Some(name) => file_config.profile(name)?.clone(),
+                None => Profile::default(),
+            },
+        };
+
+        if self.engine.is_some() || self.command.is_some() {
+            profile.engine.clone_from(&self.engine);
+            profile.command.clone_from(&self.command);
+        } else if profile.engine.is_none() && profile.command.is_none() {
+            profile.engine.clone_from(&file_config.engine);
+            profile.command.clone_from(&file_config.command);
+        }
+
+        Ok(profile)
+    }
+
+    /// The session to pick up, if one was given and it was saved before
+    fn session(&self) -> anyhow::Result<Option<Session>> {
+        match &self.session {
+            Some(path) if path.exists() => Ok(Some(Session::load(path)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Create the backend under test from the engine or command in a profile
+    fn backend(profile: &Profile, variant: Variant) -> anyhow::Result<Box<dyn Perft + Send>> {
+        if let Some(engine) = &profile.engine {
+            let mut engine = Engine::with_args(engine.to_path_buf(), &profile.args)?;
+            engine.set_variant(variant)?;
+            engine.set_castling_notation(profile.castling);
+
+            for (name, value) in &profile.options {
+                engine.set_option(name, value)?;
+            }
+
+            Ok(Box::new(engine))
+        } else if let Some(command) = &profile.command {
+            if !profile.options.is_empty() {
+                anyhow::bail!("UCI options can only be set on an engine, not on a command");
+            }
+
+            let mut executable = Executable::with_args(command.to_path_buf(), &profile.args);
+            executable.set_variant(variant);
+            executable.set_castling_notation(profile.castling);
+            Ok(Box::new(executable))
+        } else {
+            Err(anyhow::anyhow!("Provide either an engine or a command to test"))
+        }
+    }
+
+    /// Create the reference backend, running on as many threads as asked for
+    fn reference(&self, file_config: &FileConfig, variant: Variant) -> anyhow::Result<Simbelmyne> {
+        let reference = Simbelmyne::new(variant);
+
+        match self.threads.or(file_config.threads) {
+            Some(threads) => reference.with_threads(threads),
+            None => Ok(reference),
+        }
+    }
+
+    /// Load the config file, if any
+    fn file_config(&self) -> anyhow::Result<FileConfig> {
+        match &self.config {
+            Some(path) => FileConfig::load(path),
+            None => FileConfig::load_default(),
+        }
+    }
+
+    /// Resolve the theme and piece style from the command line and the config
+    /// file, in that order of precedence.
+    fn appearance(&self, file_config: &FileConfig) -> (Theme, PieceStyle) {
+        let theme_name = self.theme.or(file_config.theme).unwrap_or_default();
+        let theme = Theme::new(theme_name).with_overrides(&file_config.colors);
+        let pieces = self.pieces.or(file_config.pieces).unwrap_or_default();
+
+        (theme, pieces)
+    }
+
+    /// Resolve the key bindings from the command line and the config file, in
+    /// that order of precedence.
+    fn keymap(&self, file_config: &FileConfig) -> Keymap {
+        let preset = self.keymap.or(file_config.keymap).unwrap_or_default();
+        Keymap::new(preset).with_overrides(&file_config.keys)
+    }
+
+    /// Search for a minimal reproducer and print it to stdout
+    fn reproduce(&self) -> anyhow::Result<()> {
+        let file_config = self.file_config()?;
+        let session = self.session()?;
+        let (board, variant) = self.position(&file_config, session.as_ref())?;
+        let profile = self.profile(&file_config, session.as_ref())?;
+        let mut backend = Self::backend(&profile, variant)?;
+        let depth = self.depth(&file_config, session.as_ref())?;
+
+        let (reproducer, warnings) = find_reproducer(
+            board, 
+            depth, 
+            variant,
+            &mut backend, 
+            &mut self.reference(&file_config, variant)?, 
+            self.max_positions(&file_config)
+        )?;
+
+        for warning in warnings {
+            eprintln!("Warning: {warning}");
+        }
+
+        match reproducer {
+            Some(reproducer) => print!("{reproducer}"),
+            None => println!("No differences found at depth {depth}"),
+        }
+
+        Ok(())
+    }
+
+    /// Time perft on every position, and print the statistics to stdout
+    fn bench(&self, args: &BenchArgs) -> anyhow::Result<()> {
+        let file_config = self.file_config()?;
+        let profile = self.profile(&file_config, None)?;
+        let chess960 = self.chess960(&file_config, None);
+
+        // Without a positions file, run the starting position
+        let positions = match &args.positions {
+            Some(path) => read_positions(path)?,
+            None => {
+                let (board, variant) = self.position(&file_config, None)?;
+                let fen = variant.to_fen(&board);
+                vec![BenchPosition { fen, depth: None, counts: vec![] }]
+            }
+        };
+
+        // Without an engine or command, there's only the reference to run
+        let reference = args.reference || (profile.engine.is_none() && profile.command.is_none());
+        let mut backend_name = String::from("Reference");
+        let mut results = Vec::new();
+        let mut wrong_counts = Vec::new();
+
+        for position in &positions {
+            let (board, variant) = Variant::parse_fen(&position.fen, chess960)?;
+
+            // The depth on the command line overrides the ones in the file
+            let depth = match self.depth.or(position.depth) {
+                Some(depth) => depth,
+                None => self.depth(&file_config, None)?,
+            };
+
+            // Chess960 backends need to know about the castling rooks of every
+            // position, so start them afresh.
+            let mut backend: Box<dyn Perft + Send> = if reference {
+                Box::new(self.reference(&file_config, variant)?)
+            } else {
+                Self::backend(&profile, variant)?
+            };
+
+            if let Some(name) = backend.name() {
+                backend_name = name;
+            } else if !reference {
+                let path = profile.engine.as_ref().or(profile.command.as_ref());
+                backend_name = path.map(|path| path.display().to_string()).unwrap_or_default();
+            }
+
+            let mut nodes = 0;
+            let mut times_ms = Vec::new();
+
+            for _ in 0..args.runs {
+                let start = Instant::now();
+                nodes = backend.total(board, depth)?;
+                times_ms.push(start.elapsed().as_secs_f64() * 1000.0);
+            }
+
+            if let Some(expected) = position.expected(depth).filter(|&expected| expected != nodes) {
+                wrong_counts.push(format!(
+                    "{} (depth {depth}): found {nodes} nodes, expected {expected}",
+                    position.fen
+                ));
+            }
+
+            results.push(BenchResult::new(position.fen.clone(), depth, nodes, &times_ms));
+        }
+
+        let bench = Bench { backend: backend_name, runs: args.runs, results };
+
+        // Keep stdout clean for the JSON, and report on the baseline on stderr
+        if args.json {
+            println!("{}", serde_json::to_string_pretty(&bench)?);
+        } else {
+            print!("{bench}");
+        }
+
+        if let Some(path) = &args.save_baseline {
+            bench.save(path)?;
+        }
+
+        let mut regressions = 0;
+
+        if let Some(path) = &args.baseline {
+            let comparisons = bench.compare(&Bench::load(path)?, args.tolerance);
+
+            if args.json {
+                comparisons.iter().for_each(|comparison| eprintln!("{comparison}"));
+            } else {
+                println!();
+                println!("Compared to {}:", path.display());
+                comparisons.iter().for_each(|comparison| println!("  {comparison}"));
+            }
+
+            regressions = comparisons.iter().filter(|comparison| comparison.regressed).count();
+        }
+
+        for wrong_count in &wrong_counts {
+            eprintln!("Wrong node count for {wrong_count}");
+        }
+
+        if !wrong_counts.is_empty() {
+            anyhow::bail!("{} positions have the wrong node count", wrong_counts.len());
+        }
+
+        if regressions > 0 {
+            anyhow::bail!(
+                "{regressions} positions got more than {}% slower than the baseline",
+                args.tolerance
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Resolve a `--flag`/`--no-flag` pair, or `None` if neither was passed and
+/// the setting should come from elsewhere
+fn flag(on: bool, off: bool) -> Option<bool> {
+    match (on, off) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
 }
 
 fn main() -> anyhow::Result<()> {
-    Config::parse().run()
+    let config = Config::parse();
+
+    match &config.mode {
+        Some(Mode::Bench(args)) => config.bench(args),
+        None if config.reproduce => config.reproduce(),
+        None => config.run(),
+    }
 }
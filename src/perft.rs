@@ -1,14 +1,40 @@
 use simbelmyne_chess::{board::Board, movegen::moves::Move};
 use rayon::prelude::*;
 
-const QUIETS: bool = true;
+use crate::variant::Variant;
 
-pub fn perft(board: Board, depth: usize) -> usize {
+/// How many plies from the root the work is split across threads. Further
+/// down, the subtrees are too small to be worth the scheduling overhead, and
+/// there's already plenty of work to go around.
+const PARALLEL_PLIES: usize = 2;
+
+/// Count the nodes at the given depth, spreading the work over the current
+/// rayon thread pool.
+pub fn perft(board: Board, depth: usize, variant: Variant) -> usize {
+    perft_parallel(board, depth, variant, PARALLEL_PLIES)
+}
+
+fn perft_parallel(board: Board, depth: usize, variant: Variant, plies: usize) -> usize {
+    if plies == 0 || depth <= 1 {
+        return perft_sequential(board, depth, variant);
+    }
+
+    variant.legal_moves(&board)
+        .par_iter()
+        .map(|mv| {
+            let new_board = variant.play_move(&board, *mv);
+            perft_parallel(new_board, depth - 1, variant, plies - 1)
+        })
+        .sum()
+}
+
+/// Count the nodes at the given depth, on the current thread
+pub fn perft_sequential(board: Board, depth: usize, variant: Variant) -> usize {
     if depth == 0 {
         return 1;
     };
 
-    let moves = board.legal_moves::<QUIETS>();
+    let moves = variant.legal_moves(&board);
 
     // OPTIMIZATION: If we're at the last step, we don't need to go through
     // playing every single move and returning back, just return the number of
@@ -18,24 +44,59 @@ pub fn perft(board: Board, depth: usize) -> usize {
     }
 
     moves
-        .par_iter()
+        .iter()
         .map(|mv| {
-            let new_board = board.play_move(*mv);
-            let nodes = perft(new_board, depth - 1);
-            nodes
+            let new_board = variant.play_move(&board, *mv);
+            perft_sequential(new_board, depth - 1, variant)
         })
         .sum()
 }
 
-pub fn perft_divide(board: Board, depth: usize) -> Vec<(Move, usize)> {
-    let moves = board.legal_moves::<QUIETS>();
+/// Count the nodes below every move, spreading the work over the current
+/// rayon thread pool. There are no moves to split at depth 0.
+pub fn perft_divide(
+    board: Board,
+    depth: usize,
+    variant: Variant,
+) -> anyhow::Result<Vec<(Move, usize)>> {
+    check_divide_depth(depth)?;
+    let moves = variant.legal_moves(&board);
 
-    moves
+    Ok(moves
         .par_iter()
         .map(|&mv| {
-            let new_board = board.play_move(mv);
-            let nodes = perft(new_board, depth - 1);
+            let new_board = variant.play_move(&board, mv);
+            let nodes = perft_parallel(new_board, depth - 1, variant, PARALLEL_PLIES - 1);
+            (mv, nodes)
+        })
+        .collect())
+}
+
+/// Count the nodes below every move, on the current thread. There are no
+/// moves to split at depth 0.
+pub fn perft_divide_sequential(
+    board: Board,
+    depth: usize,
+    variant: Variant,
+) -> anyhow::Result<Vec<(Move, usize)>> {
+    check_divide_depth(depth)?;
+    let moves = variant.legal_moves(&board);
+
+    Ok(moves
+        .iter()
+        .map(|&mv| {
+            let new_board = variant.play_move(&board, mv);
+            let nodes = perft_sequential(new_board, depth - 1, variant);
             (mv, nodes)
         })
-        .collect()
+        .collect())
+}
+
+/// A split perft needs at least one ply to split the moves at
+pub fn check_divide_depth(depth: usize) -> anyhow::Result<()> {
+    if depth == 0 {
+        anyhow::bail!("A split perft needs a depth of at least 1");
+    }
+
+    Ok(())
 }
@@ -0,0 +1,135 @@
+//! A Markdown write-up of a mismatching position, with everything needed to
+//! reproduce it, so it can be pasted into an issue or a commit message as is.
+
+use std::fmt::Display;
+
+use itertools::Itertools;
+use pretty_perft::backends::PerftResult;
+use pretty_perft::explain::Explanation;
+
+/// Everything there is to know about the current position of a session
+pub struct BugReport {
+    /// The engine under test, by the name it reports, or its path
+    pub engine: String,
+
+    /// The position the session started from
+    pub root_fen: String,
+
+    /// The moves played to get to the current position
+    pub moves: Vec<String>,
+
+    /// The current position
+    pub fen: String,
+
+    /// The search depth, counted from the root
+    pub depth: usize,
+
+    /// The depth the current position is searched at
+    pub remaining_depth: usize,
+
+    /// A plain text diagram of the current position
+    pub board: String,
+
+    /// The mismatching move, along with the reasons it's wrong
+    pub mismatch: Option<(String, Explanation)>,
+
+    /// Every mismatching row of the move table, as a Markdown table
+    pub table: String,
+
+    /// The split perft output of the engine under test
+    pub found: PerftResult,
+
+    /// The split perft output of the reference
+    pub expected: PerftResult,
+
+    /// What was sent to and received from the engine under test
+    pub transcript: Vec<String>,
+
+    /// Lines of output that had to be ignored
+    pub warnings: Vec<String>,
+}
+
+impl BugReport {
+    /// Write out a split perft result, followed by its total
+    fn write_divide(f: &mut std::fmt::Formatter<'_>, divide: &PerftResult) -> std::fmt::Result {
+        writeln!(f, "```text")?;
+
+        for (mv, count) in divide {
+            writeln!(f, "{mv}: {count}")?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Total: {}", divide.iter().map(|(_, count)| count).sum::<usize>())?;
+        writeln!(f, "```")
+    }
+}
+
+impl Display for BugReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let moves = if self.moves.is_empty() {
+            String::from("none")
+        } else {
+            format!("`{}`", self.moves.join(" "))
+        };
+
+        writeln!(f, "# Perft mismatch in {}", self.engine)?;
+        writeln!(f)?;
+        writeln!(f, "- Engine: {}", self.engine)?;
+        writeln!(f, "- Root position: `{}`", self.root_fen)?;
+        writeln!(f, "- Moves: {moves}")?;
+        writeln!(f, "- Position: `{}`", self.fen)?;
+        writeln!(f, "- Depth: {} ({} from this position)", self.depth, self.remaining_depth)?;
+        writeln!(f)?;
+        writeln!(f, "```text")?;
+        writeln!(f, "{}", self.board)?;
+        writeln!(f, "```")?;
+        writeln!(f)?;
+
+        writeln!(f, "## Mismatch")?;
+        writeln!(f)?;
+
+        match &self.mismatch {
+            Some((mv, explanation)) => {
+                writeln!(f, "**{mv}**: {}", explanation.verdict.label())?;
+                writeln!(f)?;
+
+                for reason in &explanation.reasons {
+                    writeln!(f, "- {reason}")?;
+                }
+
+                writeln!(f)?;
+                writeln!(f, "{}", self.table.trim_end())?;
+            }
+
+            None => writeln!(f, "Both backends agree on every move in this position.")?,
+        }
+
+        if !self.warnings.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "Ignored output:")?;
+            writeln!(f)?;
+
+            for warning in &self.warnings {
+                writeln!(f, "- {warning}")?;
+            }
+        }
+
+        writeln!(f)?;
+        writeln!(f, "## Split perft")?;
+        writeln!(f)?;
+        writeln!(f, "{}:", self.engine)?;
+        writeln!(f)?;
+        Self::write_divide(f, &self.found)?;
+        writeln!(f)?;
+        writeln!(f, "Reference:")?;
+        writeln!(f)?;
+        Self::write_divide(f, &self.expected)?;
+        writeln!(f)?;
+
+        writeln!(f, "## Transcript")?;
+        writeln!(f)?;
+        writeln!(f, "```text")?;
+        writeln!(f, "{}", self.transcript.iter().join("\n"))?;
+        writeln!(f, "```")
+    }
+}
@@ -0,0 +1,192 @@
+//! Search the perft tree for the simplest position in which the move lists of
+//! both backends differ.
+//!
+//! Once a mismatch is known at some depth, the interesting artifact is a 
+//! single position where the backend under test generates an illegal move,
+//! misses a legal one, or generates the same move twice. Such a position can
+//! be checked with a perft of depth 1, which makes it the smallest possible
+//! reproducer for the bug.
+
+use std::fmt::Display;
+
+use itertools::Itertools;
+use simbelmyne_chess::{board::Board, movegen::moves::Move};
+
+use crate::backends::Perft;
+use crate::diff::compare;
+use crate::perft::check_divide_depth;
+use crate::variant::Variant;
+
+/// A position in which the move lists of both backends differ
+#[derive(Debug, Clone)]
+pub struct Reproducer {
+    /// The moves played from the root to get to the position
+    pub path: Vec<Move>,
+
+    /// The position itself
+    pub board: Board,
+
+    /// The rules the position is played by
+    pub variant: Variant,
+
+    /// Moves generated by the backend under test, but not by the reference
+    pub illegal: Vec<Move>,
+
+    /// Moves generated by the reference, but not by the backend under test
+    pub missing: Vec<Move>,
+
+    /// Moves generated more than once by either backend
+    pub duplicates: Vec<Move>,
+}
+
+impl Reproducer {
+    /// The number of pieces on the board, kings and pawns included
+    pub fn pieces(&self) -> u32 {
+        self.board.all_occupied().count()
+    }
+
+    /// Whether this reproducer is simpler than another one: fewer pieces on 
+    /// the board first, shorter paths second.
+    fn is_simpler_than(&self, other: &Reproducer) -> bool {
+        (self.pieces(), self.path.len()) < (other.pieces(), other.path.len())
+    }
+}
+
+impl Display for Reproducer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Path:      {}", self.path.iter().join(" "))?;
+        writeln!(f, "FEN:       {}", self.variant.to_fen(&self.board))?;
+        writeln!(f, "Pieces:    {}", self.pieces())?;
+
+        if !self.illegal.is_empty() {
+            writeln!(f, "Illegal:   {}", self.illegal.iter().join(" "))?;
+        }
+
+        if !self.missing.is_empty() {
+            writeln!(f, "Missing:   {}", self.missing.iter().join(" "))?;
+        }
+
+        if !self.duplicates.is_empty() {
+            writeln!(f, "Duplicate: {}", self.duplicates.iter().join(" "))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Search for the simplest reproducer below `board`.
+///
+/// The search follows the mismatching moves down the tree. The first branch is
+/// always followed all the way down, after which alternative mismatching 
+/// branches are tried until `max_positions` positions have been compared. 
+/// Out of all the reproducers found, the one with the fewest pieces on the 
+/// board is returned.
+///
+/// Returns `None` if no position was found where the move lists differ (e.g.,
+/// when both backends agree completely), along with any lines of the backend's
+/// output that had to be skipped along the way.
+pub fn find_reproducer<A: Perft, B: Perft>(
+    board: Board,
+    depth: usize,
+    variant: Variant,
+    found: &mut A,
+    expected: &mut B,
+    max_positions: usize,
+) -> anyhow::Result<(Option<Reproducer>, Vec<String>)> {
+    check_divide_depth(depth)?;
+
+    let mut search = Search {
+        variant,
+        found,
+        expected,
+        budget: max_positions,
+        best: None,
+        warnings: Vec::new(),
+    };
+
+    search.visit(board, depth, &mut Vec::new())?;
+
+    Ok((search.best, search.warnings))
+}
+
+struct Search<'a, A, B> {
+    variant: Variant,
+    found: &'a mut A,
+    expected: &'a mut B,
+    budget: usize,
+    best: Option<Reproducer>,
+    warnings: Vec<String>,
+}
+
+impl<A: Perft, B: Perft> Search<'_, A, B> {
+    fn visit(
+        &mut self, 
+        board: Board, 
+        depth: usize, 
+        path: &mut Vec<Move>
+    ) -> anyhow::Result<()> {
+        self.budget = self.budget.saturating_sub(1);
+        let diffs = compare(board, depth, self.found, self.expected)?;
+
+        // Skipped lines don't necessarily show up as a mismatch, so keep
+        // track of where they came from.
+        for warning in self.found.take_warnings() {
+            if path.is_empty() {
+                self.warnings.push(warning);
+            } else {
+                self.warnings.push(format!("After {}: {warning}", path.iter().join(" ")));
+            }
+        }
+
+        let illegal = diffs.iter()
+            .filter(|diff| diff.expected.is_none())
+            .map(|diff| diff.mv)
+            .collect_vec();
+
+        let missing = diffs.iter()
+            .filter(|diff| diff.found.is_none())
+            .map(|diff| diff.mv)
+            .collect_vec();
+
+        let duplicates = diffs.iter()
+            .filter(|diff| diff.is_duplicate())
+            .map(|diff| diff.mv)
+            .collect_vec();
+
+        // The move lists differ: no need to look any deeper along this branch
+        if !illegal.is_empty() || !missing.is_empty() || !duplicates.is_empty() {
+            let candidate = Reproducer {
+                path: path.clone(),
+                board,
+                variant: self.variant,
+                illegal,
+                missing,
+                duplicates,
+            };
+
+            if self.best.as_ref().is_none_or(|best| candidate.is_simpler_than(best)) {
+                self.best = Some(candidate);
+            }
+
+            return Ok(());
+        }
+
+        if depth <= 1 {
+            return Ok(());
+        }
+
+        for diff in diffs.iter().filter(|diff| diff.is_mismatch()) {
+            // Always finish the first descent, only try alternatives while 
+            // there is budget left.
+            if self.best.is_some() && self.budget == 0 {
+                break;
+            }
+
+            path.push(diff.mv);
+            self.visit(self.expected.play_move(&board, diff.mv), depth - 1, path)?;
+            path.pop();
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+//! Standard Algebraic Notation (e.g., `Nbd7`, `exd6`, `O-O`, `e8=Q+`) for moves
+//! on a given board.
+//!
+//! SAN depends on the rest of the position (disambiguation, checks), so it can
+//! only be computed for moves that are actually legal according to the
+//! reference.
+
+use simbelmyne_chess::board::Board;
+use simbelmyne_chess::movegen::moves::{Move, MoveType};
+use simbelmyne_chess::piece::PieceType;
+use simbelmyne_chess::square::Square;
+
+use crate::explain::is_castle_attempt;
+use crate::variant::Variant;
+
+/// Fill in the move metadata (captures, double pushes, en-passant) for a bare
+/// move, based on the current board.
+pub fn annotate(board: &Board, mv: Move) -> Move {
+    use MoveType::*;
+    let src = mv.src();
+    let tgt = mv.tgt();
+    let is_pawn = board.get_at(src).is_some_and(|piece| piece.is_pawn());
+    let is_capture = board.get_at(tgt).is_some();
+
+    let mtype = match mv.get_promo_type() {
+        Some(PieceType::Knight) if is_capture => KnightPromoCapture,
+        Some(PieceType::Bishop) if is_capture => BishopPromoCapture,
+        Some(PieceType::Rook) if is_capture => RookPromoCapture,
+        Some(PieceType::Queen) if is_capture => QueenPromoCapture,
+        Some(_) => mv.get_type(),
+        None if is_capture => Capture,
+        None if is_pawn && board.en_passant == Some(tgt) => EnPassant,
+        None if is_pawn && src.file() == tgt.file() && src.rank().abs_diff(tgt.rank()) == 2 => DoublePush,
+        None => Quiet,
+    };
+
+    Move::new(src, tgt, mtype)
+}
+
+/// Write out a move in SAN, or `None` if the move isn't legal on this board.
+pub fn san(board: &Board, variant: Variant, mv: Move) -> Option<String> {
+    let mv = variant.find_legal(board, mv)?;
+    let piece = board.get_at(mv.src())?;
+    let mut san = String::new();
+
+    if mv.is_castle() {
+        san.push_str(if mv.tgt().file() > mv.src().file() { "O-O" } else { "O-O-O" });
+    } else if piece.is_pawn() {
+        if mv.is_capture() {
+            san.push(file_char(mv.src()));
+            san.push('x');
+        }
+
+        san.push_str(&mv.tgt().to_string());
+
+        if let Some(ptype) = mv.get_promo_type() {
+            san.push('=');
+            san.push(ptype_char(ptype));
+        }
+    } else {
+        san.push(ptype_char(piece.piece_type()));
+        san.push_str(&disambiguation(board, variant, mv));
+
+        if mv.is_capture() {
+            san.push('x');
+        }
+
+        san.push_str(&mv.tgt().to_string());
+    }
+
+    san.push_str(check_suffix(board, variant, mv));
+    Some(san)
+}
+
+/// A compact marker for the kind of move: `x` for captures, `ep` for 
+/// en-passant, `O-O`/`O-O-O` for castling, `=Q` (etc...) for promotions, and 
+/// `+`/`#` for checks and mates. Quiet moves get an empty marker.
+///
+/// This also works for moves that aren't legal, so illegal moves can be
+/// classified as well. Checks are only marked for legal moves, though.
+pub fn marker(board: &Board, variant: Variant, mv: Move) -> String {
+    let legal = variant.find_legal(board, mv);
+    let mv = legal.unwrap_or_else(|| annotate(board, mv));
+    let mut marker = String::new();
+
+    // Illegal castles don't get annotated, so recognize them by the king 
+    // moving sideways from its castling square.
+    if mv.is_castle() || is_castle_attempt(board, variant, mv) {
+        marker.push_str(if mv.tgt().file() > mv.src().file() { "O-O" } else { "O-O-O" });
+    } else if mv.is_en_passant() {
+        marker.push_str("ep");
+    } else if mv.is_capture() {
+        marker.push('x');
+    }
+
+    if let Some(ptype) = mv.get_promo_type() {
+        marker.push('=');
+        marker.push(ptype_char(ptype));
+    }
+
+    if let Some(mv) = legal {
+        marker.push_str(check_suffix(board, variant, mv));
+    }
+
+    marker
+}
+
+/// `+` if the (legal) move gives check, `#` if it gives mate
+fn check_suffix(board: &Board, variant: Variant, mv: Move) -> &'static str {
+    let next = variant.play_move(board, mv);
+
+    if !next.in_check() {
+        ""
+    } else if variant.legal_moves(&next).is_empty() {
+        "#"
+    } else {
+        "+"
+    }
+}
+
+/// The file and/or rank needed to tell a move apart from moves by other pieces
+/// of the same type to the same square.
+fn disambiguation(board: &Board, variant: Variant, mv: Move) -> String {
+    let piece = board.get_at(mv.src());
+
+    let others = variant.legal_moves(board)
+        .into_iter()
+        .filter(|other| other.tgt() == mv.tgt() && other.src() != mv.src())
+        .filter(|other| board.get_at(other.src()) == piece)
+        .map(|other| other.src())
+        .collect::<Vec<_>>();
+
+    if others.is_empty() {
+        String::new()
+    } else if others.iter().all(|sq| sq.file() != mv.src().file()) {
+        file_char(mv.src()).to_string()
+    } else if others.iter().all(|sq| sq.rank() != mv.src().rank()) {
+        (mv.src().rank() + 1).to_string()
+    } else {
+        mv.src().to_string()
+    }
+}
+
+fn file_char(square: Square) -> char {
+    (b'a' + square.file() as u8) as char
+}
+
+fn ptype_char(ptype: PieceType) -> char {
+    match ptype {
+        PieceType::Pawn => 'P',
+        PieceType::Knight => 'N',
+        PieceType::Bishop => 'B',
+        PieceType::Rook => 'R',
+        PieceType::Queen => 'Q',
+        PieceType::King => 'K',
+    }
+}
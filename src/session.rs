@@ -0,0 +1,167 @@
+//! A snapshot of an exploration of the perft tree, so it can be picked back 
+//! up later with `--session <file>`.
+//!
+//! ```toml
+//! fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"
+//! chess960 = false
+//! depth = 5
+//! moves = ["e1g1", "b4c3"]
+//! selected = "d2c3"
+//! notes = "Castling rights aren't revoked when the rook is captured?"
+//!
+//! [backend]
+//! engine = "/home/me/dev/engine/target/release/engine"
+//! castling = "shredder"
+//!
+//! [backend.options]
+//! Hash = 64
+//!
+//! [[bookmarks]]
+//! moves = ["e1g1"]
+//! fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R4RK1 b kq - 1 1"
+//! note = "Off by one"
+//!
+//! [[timings]]
+//! fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R4RK1 b kq - 1 1"
+//! depth = 2
+//! nodes = 2059
+//! found_ms = 4.2
+//! expected_ms = 1.3
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Profile;
+
+/// Where sessions are saved when no `--session` file was given
+pub const DEFAULT_SESSION: &str = "pretty-perft-session.toml";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Session {
+    /// The root of the tree
+    pub fen: String,
+
+    #[serde(default)]
+    pub chess960: bool,
+
+    /// The search depth, counted from the root
+    pub depth: usize,
+
+    /// The moves played to get from the root to the current position
+    #[serde(default)]
+    pub moves: Vec<String>,
+
+    /// The move selected in the table
+    #[serde(default)]
+    pub selected: Option<String>,
+
+    #[serde(default)]
+    pub notes: String,
+
+    /// The engine or script under test
+    #[serde(default)]
+    pub backend: Profile,
+
+    #[serde(default)]
+    pub bookmarks: Vec<Bookmark>,
+
+    /// How long every position took to search, oldest first
+    #[serde(default)]
+    pub timings: Vec<TimingRecord>,
+}
+
+/// A position in the tree worth coming back to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Bookmark {
+    /// The moves played from the root to get to the position
+    pub moves: Vec<String>,
+    pub fen: String,
+
+    #[serde(default)]
+    pub note: String,
+}
+
+/// How long both backends took to search a position, to spot slowdowns from
+/// one build of the engine to the next
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TimingRecord {
+    pub fen: String,
+
+    /// The depth the position was searched at
+    pub depth: usize,
+
+    /// The number of nodes the reference found
+    pub nodes: usize,
+
+    /// Wall-clock time of the backend under test, in milliseconds
+    pub found_ms: f64,
+
+    /// Wall-clock time of the reference, in milliseconds
+    pub expected_ms: f64,
+}
+
+impl Bookmark {
+    /// Write the bookmark as a line of a perft suite, with the node counts 
+    /// for each depth, starting from 1. The moves and the note are added as 
+    /// `id` and `c0` operations:
+    ///
+    /// ```text
+    /// <fen> ;D1 43 ;D2 2059 ;id "e1g1" ;c0 "Off by one"
+    /// ```
+    pub fn to_epd(&self, counts: &[usize]) -> String {
+        let mut line = self.fen.clone();
+
+        for (depth, count) in counts.iter().enumerate() {
+            line.push_str(&format!(" ;D{} {count}", depth + 1));
+        }
+
+        if !self.moves.is_empty() {
+            line.push_str(&format!(" ;id \"{}\"", self.moves.join(" ")));
+        }
+
+        if !self.note.is_empty() {
+            line.push_str(&format!(" ;c0 \"{}\"", self.note.replace('"', "'")));
+        }
+
+        line
+    }
+}
+
+impl Session {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read session file {}", path.display()))?;
+
+        toml::from_str(&contents)
+            .with_context(|| format!("Invalid session file {}", path.display()))
+    }
+
+    /// Write the session to a file. Paths to the backend are stored as 
+    /// absolute paths, so the session can be reopened from anywhere.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let mut session = self.clone();
+        session.backend.engine = session.backend.engine.map(absolute);
+        session.backend.command = session.backend.command.map(absolute);
+
+        let contents = toml::to_string(&session)?;
+
+        std::fs::write(path, contents)
+            .with_context(|| format!("Failed to write session file {}", path.display()))
+    }
+}
+
+/// Bare names (e.g., `stockfish`) are left alone, so they're still looked up
+/// in the `PATH`.
+fn absolute(path: PathBuf) -> PathBuf {
+    if path.components().count() > 1 {
+        std::path::absolute(&path).unwrap_or(path)
+    } else {
+        path
+    }
+}
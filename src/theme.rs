@@ -0,0 +1,258 @@
+use clap::ValueEnum;
+use ratatui::style::Color;
+use serde::Deserialize;
+use simbelmyne_chess::piece::{Color as Side, Piece, PieceType};
+
+/// How to draw the pieces on the board
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum PieceStyle {
+    /// Algebraic letters (K, q, n, ...)
+    #[default]
+    Ascii,
+
+    /// Unicode chess glyphs (♔, ♛, ♞, ...)
+    Unicode,
+
+    /// Nerd Font chess glyphs. These look the same for both sides, so they're
+    /// best paired with a theme that sets distinct piece colors.
+    Nerd,
+}
+
+impl PieceStyle {
+    pub fn glyph(self, piece: Piece) -> String {
+        use PieceType::*;
+        let white = piece.color() == Side::White;
+
+        let glyph = match (self, piece.piece_type()) {
+            (PieceStyle::Ascii, _) => return piece.to_string(),
+
+            (PieceStyle::Unicode, King) => if white { "♔" } else { "♚" },
+            (PieceStyle::Unicode, Queen) => if white { "♕" } else { "♛" },
+            (PieceStyle::Unicode, Rook) => if white { "♖" } else { "♜" },
+            (PieceStyle::Unicode, Bishop) => if white { "♗" } else { "♝" },
+            (PieceStyle::Unicode, Knight) => if white { "♘" } else { "♞" },
+            (PieceStyle::Unicode, Pawn) => if white { "♙" } else { "♟" },
+
+            (PieceStyle::Nerd, King) => "\u{f0857}",
+            (PieceStyle::Nerd, Knight) => "\u{f0858}",
+            (PieceStyle::Nerd, Pawn) => "\u{f0859}",
+            (PieceStyle::Nerd, Queen) => "\u{f085a}",
+            (PieceStyle::Nerd, Rook) => "\u{f085b}",
+            (PieceStyle::Nerd, Bishop) => "\u{f085c}",
+        };
+
+        glyph.to_string()
+    }
+}
+
+/// The built-in color themes
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeName {
+    /// For terminals with a dark background
+    #[default]
+    Dark,
+
+    /// For terminals with a light background
+    Light,
+
+    /// Brighter text and borders, and a board with solid squares
+    HighContrast,
+
+    /// Avoids relying on red/green to tell matches and mismatches apart
+    Colorblind,
+}
+
+/// All the colors used throughout the TUI
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Theme {
+    /// Panel titles and the selected row
+    pub text: Color,
+
+    /// Borders, labels and rows that need no attention
+    pub muted: Color,
+
+    /// Table headers, keybindings and information labels
+    pub accent: Color,
+
+    /// Rows where the backends disagree
+    pub mismatch: Color,
+
+    /// Things that deserve attention, but aren't necessarily wrong
+    pub warning: Color,
+
+    pub light_square: Color,
+    pub dark_square: Color,
+    pub white_piece: Color,
+    pub black_piece: Color,
+
+    /// Source and target squares of the selected move
+    pub highlight: Color,
+
+    /// Board overlays
+    pub attacked: Color,
+    pub checkers: Color,
+    pub pinned: Color,
+    pub pinray: Color,
+    pub en_passant: Color,
+    pub castling: Color,
+}
+
+impl Theme {
+    pub fn new(name: ThemeName) -> Self {
+        match name {
+            ThemeName::Dark => Self::DARK,
+            ThemeName::Light => Self::LIGHT,
+            ThemeName::HighContrast => Self::HIGH_CONTRAST,
+            ThemeName::Colorblind => Self::COLORBLIND,
+        }
+    }
+
+    pub const DARK: Theme = Theme {
+        text: Color::White,
+        muted: Color::DarkGray,
+        accent: Color::Blue,
+        mismatch: Color::Red,
+        warning: Color::Yellow,
+        light_square: Color::Reset,
+        dark_square: Color::DarkGray,
+        white_piece: Color::Reset,
+        black_piece: Color::Reset,
+        highlight: Color::Blue,
+        attacked: Color::Red,
+        checkers: Color::Red,
+        pinned: Color::Yellow,
+        pinray: Color::LightYellow,
+        en_passant: Color::Green,
+        castling: Color::Cyan,
+    };
+
+    pub const LIGHT: Theme = Theme {
+        text: Color::Black,
+        muted: Color::Gray,
+        accent: Color::Blue,
+        mismatch: Color::Red,
+        warning: Color::Magenta,
+        light_square: Color::Reset,
+        dark_square: Color::Gray,
+        white_piece: Color::Reset,
+        black_piece: Color::Reset,
+        highlight: Color::LightBlue,
+        attacked: Color::Red,
+        checkers: Color::LightRed,
+        pinned: Color::Yellow,
+        pinray: Color::LightYellow,
+        en_passant: Color::LightGreen,
+        castling: Color::LightCyan,
+    };
+
+    pub const HIGH_CONTRAST: Theme = Theme {
+        text: Color::White,
+        muted: Color::Gray,
+        accent: Color::LightCyan,
+        mismatch: Color::LightRed,
+        warning: Color::LightYellow,
+        light_square: Color::Rgb(240, 217, 181),
+        dark_square: Color::Rgb(181, 136, 99),
+        white_piece: Color::Rgb(255, 255, 255),
+        black_piece: Color::Rgb(0, 0, 0),
+        highlight: Color::LightBlue,
+        attacked: Color::Red,
+        checkers: Color::LightRed,
+        pinned: Color::LightYellow,
+        pinray: Color::Yellow,
+        en_passant: Color::LightGreen,
+        castling: Color::LightCyan,
+    };
+
+    /// Based on the Okabe-Ito palette, which stays distinguishable for the
+    /// common types of color blindness.
+    pub const COLORBLIND: Theme = Theme {
+        text: Color::White,
+        muted: Color::DarkGray,
+        accent: Color::Rgb(86, 180, 233),
+        mismatch: Color::Rgb(230, 159, 0),
+        warning: Color::Rgb(240, 228, 66),
+        light_square: Color::Reset,
+        dark_square: Color::DarkGray,
+        white_piece: Color::Reset,
+        black_piece: Color::Reset,
+        highlight: Color::Rgb(0, 114, 178),
+        attacked: Color::Rgb(213, 94, 0),
+        checkers: Color::Rgb(213, 94, 0),
+        pinned: Color::Rgb(240, 228, 66),
+        pinray: Color::Rgb(204, 121, 167),
+        en_passant: Color::Rgb(0, 158, 115),
+        castling: Color::Rgb(86, 180, 233),
+    };
+
+    /// The foreground color for a piece
+    pub fn piece(&self, piece: Piece) -> Color {
+        match piece.color() {
+            Side::White => self.white_piece,
+            Side::Black => self.black_piece,
+        }
+    }
+
+    /// Replace any of the colors that were overridden
+    pub fn with_overrides(mut self, overrides: &ColorOverrides) -> Self {
+        let fields = [
+            (&mut self.text, overrides.text),
+            (&mut self.muted, overrides.muted),
+            (&mut self.accent, overrides.accent),
+            (&mut self.mismatch, overrides.mismatch),
+            (&mut self.warning, overrides.warning),
+            (&mut self.light_square, overrides.light_square),
+            (&mut self.dark_square, overrides.dark_square),
+            (&mut self.white_piece, overrides.white_piece),
+            (&mut self.black_piece, overrides.black_piece),
+            (&mut self.highlight, overrides.highlight),
+            (&mut self.attacked, overrides.attacked),
+            (&mut self.checkers, overrides.checkers),
+            (&mut self.pinned, overrides.pinned),
+            (&mut self.pinray, overrides.pinray),
+            (&mut self.en_passant, overrides.en_passant),
+            (&mut self.castling, overrides.castling),
+        ];
+
+        for (color, value) in fields {
+            if let Some(value) = value {
+                *color = value;
+            }
+        }
+
+        self
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::DARK
+    }
+}
+
+/// Individual colors to override on top of a built-in theme.
+///
+/// Colors can be given by name ("red", "dark-gray"), as a hex string
+/// ("#e69f00") or as an ANSI index ("208").
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorOverrides {
+    pub text: Option<Color>,
+    pub muted: Option<Color>,
+    pub accent: Option<Color>,
+    pub mismatch: Option<Color>,
+    pub warning: Option<Color>,
+    pub light_square: Option<Color>,
+    pub dark_square: Option<Color>,
+    pub white_piece: Option<Color>,
+    pub black_piece: Option<Color>,
+    pub highlight: Option<Color>,
+    pub attacked: Option<Color>,
+    pub checkers: Option<Color>,
+    pub pinned: Option<Color>,
+    pub pinray: Option<Color>,
+    pub en_passant: Option<Color>,
+    pub castling: Option<Color>,
+}
@@ -1,202 +1,1022 @@
-pub(crate) use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{Event, MouseButton, MouseEventKind};
 
-use crossterm::event::KeyCode;
 use ratatui::prelude::Constraint;
-use ratatui::style::Color;
 use ratatui::style::Style;
 use ratatui::text::Line;
 use ratatui::text::Span;
 use ratatui::text::Text;
 use ratatui::{
-    prelude::{CrosstermBackend, Direction, Layout, Rect},
+    prelude::{CrosstermBackend, Direction, Layout, Position, Rect},
     Frame, Terminal,
 };
-use simbelmyne_chess::{board::Board, movegen::moves::Move};
+use simbelmyne_chess::board::Board;
+use simbelmyne_chess::movegen::moves::{Move, MoveType};
+use simbelmyne_chess::piece::PieceType;
+use simbelmyne_chess::square::Square;
+use tui_input::backend::crossterm::to_input_request;
+use tui_input::{Input, InputRequest};
+
+use anyhow::anyhow;
+
+use pretty_perft::backends::{Perft, Simbelmyne};
+use pretty_perft::backends::{Divide, PerftThread, Timing};
+use pretty_perft::diff::{compare_results, Diff};
+use pretty_perft::explain::{explain, is_castle_attempt, why_invalid};
+use pretty_perft::variant::Variant;
 
-use crate::components::centered;
-use crate::backends::Engine;
-use crate::backends::Executable;
-use crate::backends::Simbelmyne;
-use crate::backends::PerftThread;
+use crate::clipboard::{copy, Clip, ClipboardMode, Copied};
+use crate::config::Profile;
+use crate::keymap::{Key, Keymap};
+use crate::report::BugReport;
+use crate::session::{Bookmark, Session, TimingRecord, DEFAULT_SESSION};
 use crate::Config;
+use crate::theme::{PieceStyle, Theme};
 
 use crate::components::{
-    board_view::BoardView,
-    diff_table::DiffTable,
+    board_view::{BoardView, Overlay, Overlays},
+    bookmark_view::BookmarkView,
+    detail_view::DetailView,
+    help_view::HelpView,
+    diff_table::{matches_query, to_markdown, to_text, DiffTable, SortOrder},
     info_view::InfoView,
 };
-pub type PerftResult = Vec<(Move, usize)>;
-
-#[derive(Debug, Clone)]
-pub struct Diff {
-    pub mv: Move,
-    pub found: Option<usize>,
-    pub expected: Option<usize>,
-}
 
 pub struct State {
     engine: PerftThread,
     simbelmyne: PerftThread,
-    expected: Arc<Mutex<PerftResult>>,
-    found: Arc<Mutex<PerftResult>>,
+
+    /// The reference, to count nodes outside of the table
+    reference: Simbelmyne,
+    expected: Arc<Mutex<Divide>>,
+    found: Arc<Mutex<Divide>>,
     diffs: Vec<Diff>,
+
+    /// Output from either backend that had to be ignored
+    warnings: Vec<String>,
+    total_found: usize,
+    total_expected: usize,
+
+    /// How long both backends took on the current position, once both are done
+    timing: Option<(Timing, Timing)>,
+
+    /// How long every position took to search, saved with the session
+    timings: Vec<TimingRecord>,
     selected: usize,
+
+    /// How many rows the move table is scrolled down
+    table_offset: usize,
+    sort: SortOrder,
+    mismatches_only: bool,
+    search: Input,
+    searching: bool,
     depth: usize,
     initial_board: Board,
     board_stack: Vec<Board>,
+
+    /// The moves played to get from the initial board to the current one
+    path: Vec<Move>,
+    variant: Variant,
+    overlays: Overlays,
+    flipped: bool,
+    orient_to_move: bool,
+    theme: Theme,
+    pieces: PieceStyle,
+    keymap: Keymap,
+    show_help: bool,
+
+    /// The square clicked as the source of a move, waiting for a target
+    pending: Option<Square>,
+
+    /// The table row clicked last, and when, to spot double-clicks
+    last_click: Option<(usize, Instant)>,
+
+    /// Where the panels were drawn last, to find out what was clicked
+    layout: Option<LayoutChunks>,
+
+    /// The text being typed at the bottom of the screen, if any
+    prompt: Option<Prompt>,
+    prompt_input: Input,
+
+    /// A message to show at the bottom of the screen, until the next key press
+    status: Option<String>,
+
+    /// Where to save the session
+    session_path: PathBuf,
+
+    /// The engine or script under test, to save along with the session
+    backend: Profile,
+    notes: String,
+    bookmarks: Vec<Bookmark>,
+    show_bookmarks: bool,
+    selected_bookmark: usize,
+
+    /// Progress reports from the bookmark export, while it's running
+    export: Option<Receiver<String>>,
+    clipboard: ClipboardMode,
+
+    /// The move to select once the moves of a restored session come in
+    restore_selection: Option<Move>,
     should_quit: bool,
 }
 
+/// What's being typed in the prompt at the bottom of the screen
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Prompt {
+    Notes,
+    Bookmark,
+}
+
+impl Prompt {
+    fn label(self) -> &'static str {
+        match self {
+            Prompt::Notes => "Notes",
+            Prompt::Bookmark => "Bookmark note",
+        }
+    }
+}
+
 impl State {
-    fn new(depth: usize, fen: String, engine: PerftThread) -> State {
-        let initial_board = fen.parse().unwrap();
-        let simbelmyne = PerftThread::new(Simbelmyne {});
+    fn new(
+        depth: usize,
+        initial_board: Board,
+        variant: Variant,
+        engine: PerftThread,
+        orient_to_move: bool,
+        theme: Theme,
+        pieces: PieceStyle,
+    ) -> State {
+        let reference = Simbelmyne::new(variant);
+        let simbelmyne = PerftThread::new(reference.clone());
 
         Self {
             engine,
             simbelmyne,
-            expected: Arc::new(Mutex::new(Vec::new())),
-            found: Arc::new(Mutex::new(Vec::new())),
+            reference,
+            expected: Arc::new(Mutex::new(Divide::default())),
+            found: Arc::new(Mutex::new(Divide::default())),
             diffs: vec![],
+            warnings: vec![],
+            total_found: 0,
+            total_expected: 0,
+            timing: None,
+            timings: vec![],
             selected: 0,
+            table_offset: 0,
+            sort: SortOrder::default(),
+            mismatches_only: false,
+            search: Input::default(),
+            searching: false,
             depth,
             initial_board,
             board_stack: vec![initial_board],
+            path: vec![],
+            variant,
+            overlays: Overlays::default(),
+            flipped: false,
+            orient_to_move,
+            theme,
+            pieces,
+            keymap: Keymap::default(),
+            show_help: false,
+            pending: None,
+            last_click: None,
+            layout: None,
+            prompt: None,
+            prompt_input: Input::default(),
+            status: None,
+            session_path: PathBuf::from(DEFAULT_SESSION),
+            backend: Profile::default(),
+            notes: String::new(),
+            bookmarks: vec![],
+            show_bookmarks: false,
+            selected_bookmark: 0,
+            export: None,
+            clipboard: ClipboardMode::default(),
+            restore_selection: None,
             should_quit: false,
         }
     }
 
+    fn with_keymap(self, keymap: Keymap) -> Self {
+        Self { keymap, ..self }
+    }
+
+    fn with_session(self, session_path: PathBuf, backend: Profile) -> Self {
+        Self { session_path, backend, ..self }
+    }
+
+    fn with_clipboard(self, clipboard: ClipboardMode) -> Self {
+        Self { clipboard, ..self }
+    }
+
+    fn with_reference(self, reference: Simbelmyne) -> Self {
+        let simbelmyne = PerftThread::new(reference.clone());
+        Self { simbelmyne, reference, ..self }
+    }
+
+    /// Replay the moves of a saved session, and pick the notes, bookmarks and
+    /// selected move back up.
+    fn restore(&mut self, session: &Session) -> anyhow::Result<()> {
+        self.check_reachable(&session.moves)?;
+        (self.board_stack, self.path) = self.follow(&session.moves)?;
+
+        let board = self.board_stack.last().unwrap();
+        self.restore_selection = session.selected
+            .as_deref()
+            .map(parse_move)
+            .transpose()?
+            .map(|mv| self.variant.normalize(board, mv));
+
+        self.notes.clone_from(&session.notes);
+        self.bookmarks.clone_from(&session.bookmarks);
+        self.timings.clone_from(&session.timings);
+        Ok(())
+    }
+
+    /// Check that a list of moves leaves some depth to search at, given the 
+    /// current search depth.
+    fn check_reachable(&self, moves: &[String]) -> anyhow::Result<()> {
+        if moves.len() >= self.depth {
+            return Err(anyhow!(
+                "{} moves were played, which doesn't leave anything to search \
+                at depth {}",
+                moves.len(),
+                self.depth
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Play a list of moves from the initial board, returning every board 
+    /// along the way, along with the moves as they were played.
+    ///
+    /// Fails on the first move that isn't legal, e.g., when the moves were
+    /// saved for another starting position.
+    fn follow(&self, moves: &[String]) -> anyhow::Result<(Vec<Board>, Vec<Move>)> {
+        let mut boards = vec![self.initial_board];
+        let mut path = vec![];
+
+        for (ply, mv) in moves.iter().enumerate() {
+            let board = boards.last().unwrap();
+            let mv = self.variant.normalize(board, parse_move(mv)?);
+
+            if !self.variant.legal_moves(board).contains(&mv) {
+                return Err(anyhow!(
+                    "Move {mv} (ply {}) isn't legal in {}",
+                    ply + 1,
+                    self.variant.to_fen(board)
+                ));
+            }
+
+            boards.push(self.variant.play_move(board, mv));
+            path.push(mv);
+        }
+
+        Ok((boards, path))
+    }
+
+    /// Rerun both backends after moving to another position
+    fn enter_position(&mut self) {
+        self.clear_search();
+        self.run_perft();
+        self.refresh_diff().unwrap();
+        self.selected = 0;
+        self.pending = None;
+    }
+
+    fn add_bookmark(&mut self, note: String) {
+        let board = self.board_stack.last().unwrap();
+
+        self.bookmarks.push(Bookmark {
+            moves: self.path.iter().map(Move::to_string).collect(),
+            fen: self.variant.to_fen(board),
+            note,
+        });
+
+        self.status = Some(format!("Bookmarked {}", self.variant.to_fen(board)));
+    }
+
+    fn jump_to_bookmark(&mut self) {
+        let Some(bookmark) = self.bookmarks.get(self.selected_bookmark) else {
+            return;
+        };
+
+        let followed = self.check_reachable(&bookmark.moves)
+            .and_then(|_| self.follow(&bookmark.moves));
+
+        match followed {
+            Ok((boards, path)) => {
+                self.board_stack = boards;
+                self.path = path;
+                self.show_bookmarks = false;
+                self.enter_position();
+            }
+
+            Err(err) => self.status = Some(format!("{err:#}")),
+        }
+    }
+
+    fn delete_bookmark(&mut self) {
+        if self.selected_bookmark < self.bookmarks.len() {
+            self.bookmarks.remove(self.selected_bookmark);
+            self.selected_bookmark = self.selected_bookmark.min(self.bookmarks.len().saturating_sub(1));
+        }
+    }
+
+    fn copy(&mut self, clip: Clip) {
+        let board = self.board_stack.last().unwrap();
+
+        let text = match clip {
+            Clip::Fen => self.variant.to_fen(board),
+            Clip::UciPosition => {
+                // Use the notation the engine under test expects
+                let fen = self.variant.to_fen_as(&self.initial_board, self.backend.castling);
+                let moves = self.path.iter().map(Move::to_string).collect::<Vec<_>>();
+
+                if moves.is_empty() {
+                    format!("position fen {fen}")
+                } else {
+                    format!("position fen {fen} moves {}", moves.join(" "))
+                }
+            }
+            Clip::Table => to_text(board, self.variant, &self.diffs),
+            Clip::MarkdownTable => to_markdown(board, self.variant, &self.diffs),
+        };
+
+        self.status = Some(match copy(&text, clip, self.clipboard) {
+            Ok(Copied::Clipboard) => format!("Copied {} to the clipboard", clip.label()),
+            Ok(Copied::File(path)) => format!("Wrote {} to {}", clip.label(), path.display()),
+            Err(err) => format!("Failed to copy {}: {err:#}", clip.label()),
+        });
+    }
+
+    /// Write the bookmarks to an EPD file next to the session, along with the
+    /// reference node counts down to the search depth. Counting the nodes can
+    /// take a while, so it happens on a thread of its own, which reports its
+    /// progress in the status line.
+    fn export_bookmarks(&mut self) {
+        if self.export.is_some() {
+            self.status = Some(String::from("Still exporting the bookmarks"));
+            return;
+        }
+
+        if self.bookmarks.is_empty() {
+            self.status = Some(String::from("Nothing bookmarked yet"));
+            return;
+        }
+
+        let path = self.session_path.with_extension("epd");
+
+        // Replay the bookmarks right away, so broken ones are reported before
+        // any counting starts
+        let positions = self.bookmarks
+            .iter()
+            .map(|bookmark| {
+                let (boards, path) = self.follow(&bookmark.moves)?;
+                let board = *boards.last().unwrap();
+                let remaining_depth = self.depth.saturating_sub(path.len()).max(1);
+
+                Ok((bookmark.clone(), board, remaining_depth))
+            })
+            .collect::<anyhow::Result<Vec<_>>>();
+
+        let positions = match positions {
+            Ok(positions) => positions,
+            Err(err) => {
+                self.status = Some(format!("Failed to export the bookmarks: {err:#}"));
+                return;
+            }
+        };
+
+        let mut reference = self.reference.clone();
+        let (tx, rx) = channel();
+
+        std::thread::spawn(move || {
+            let count = positions.len();
+
+            let lines = positions
+                .iter()
+                .enumerate()
+                .map(|(idx, (bookmark, board, remaining_depth))| {
+                    let counts = (1..=*remaining_depth)
+                        .map(|depth| {
+                            let _ = tx.send(format!(
+                                "Exporting bookmark {} of {count}, at depth {depth} of \
+                                {remaining_depth}...",
+                                idx + 1
+                            ));
+
+                            reference.total(*board, depth)
+                        })
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+
+                    Ok(bookmark.to_epd(&counts) + "\n")
+                })
+                .collect::<anyhow::Result<String>>()
+                .and_then(|lines| Ok(std::fs::write(&path, lines)?));
+
+            let _ = tx.send(match lines {
+                Ok(()) => format!("Exported {count} bookmarks to {}", path.display()),
+                Err(err) => format!("Failed to export the bookmarks: {err:#}"),
+            });
+        });
+
+        self.export = Some(rx);
+        self.status = Some(String::from("Exporting the bookmarks..."));
+    }
+
+    /// Show how the bookmark export is getting on, and how it ended
+    fn poll_export(&mut self) {
+        let Some(export) = &self.export else { return };
+
+        loop {
+            match export.try_recv() {
+                Ok(status) => self.status = Some(status),
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => break,
+            }
+        }
+
+        self.export = None;
+    }
+
+    /// Write everything about the current position to a Markdown file next
+    /// to the session, to paste into a bug report.
+    fn export_report(&mut self) {
+        let board = self.board_stack.last().unwrap();
+        let found = self.found.lock().unwrap().clone();
+        let expected = self.expected.lock().unwrap().clone();
+
+        let mut mismatches = compare_results(board, &found.moves, &expected.moves, &self.reference);
+        mismatches.retain(Diff::is_mismatch);
+        self.sort.sort(&mut mismatches);
+
+        // Report the selected move if it's one of the culprits
+        let mismatch = self.diffs
+            .get(self.selected)
+            .filter(|diff| diff.is_mismatch())
+            .or(mismatches.first())
+            .map(|diff| (diff.mv.to_string(), explain(board, self.variant, diff)));
+
+        let engine = match (self.engine.name(), &self.backend.engine, &self.backend.command) {
+            (Some(name), _, _) => name.to_owned(),
+            (None, Some(path), _) | (None, None, Some(path)) => path.display().to_string(),
+            (None, None, None) => String::from("the engine under test"),
+        };
+
+        let report = BugReport {
+            engine,
+            root_fen: self.variant.to_fen(&self.initial_board),
+            moves: self.path.iter().map(Move::to_string).collect(),
+            fen: self.variant.to_fen(board),
+            depth: self.depth,
+            remaining_depth: self.remaining_depth(),
+            board: self.board_view().to_ascii(),
+            mismatch,
+            table: to_markdown(board, self.variant, &mismatches),
+            warnings: self.warnings.clone(),
+            found: found.moves,
+            expected: expected.moves,
+            transcript: found.transcript,
+        };
+
+        let path = self.session_path.with_extension("md");
+
+        self.status = Some(match std::fs::write(&path, report.to_string()) {
+            Ok(()) => format!("Wrote a bug report to {}", path.display()),
+            Err(err) => format!("Failed to write the bug report: {err:#}"),
+        });
+    }
+
+    fn save_session(&mut self) {
+        let session = Session {
+            fen: self.variant.to_fen(&self.initial_board),
+            chess960: matches!(self.variant, Variant::Chess960(_)),
+            depth: self.depth,
+            moves: self.path.iter().map(Move::to_string).collect(),
+            selected: self.diffs.get(self.selected).map(|diff| diff.mv.to_string()),
+            notes: self.notes.clone(),
+            backend: self.backend.clone(),
+            bookmarks: self.bookmarks.clone(),
+            timings: self.timings.clone(),
+        };
+
+        self.status = Some(match session.save(&self.session_path) {
+            Ok(()) => format!("Saved the session to {}", self.session_path.display()),
+            Err(err) => format!("{err:#}"),
+        });
+    }
+
+    /// The depth to search the current position at: the search depth counts
+    /// from the starting position, so every move played takes a ply off.
+    fn remaining_depth(&self) -> usize {
+        self.depth.saturating_sub(self.path.len())
+    }
+
     fn run_perft(&mut self) {
         let board = self.board_stack.last().unwrap();
-        let current_depth = self.board_stack.len();
-        let remaining_depth = self.depth.saturating_sub(current_depth);
+        let remaining_depth = self.remaining_depth();
+
+        // Don't mistake the timings of the previous run for this one's
+        self.timing = None;
+        self.found.lock().unwrap().timing = None;
+        self.expected.lock().unwrap().timing = None;
 
         self.engine.run(*board, remaining_depth, self.found.clone());
         self.simbelmyne.run(*board, remaining_depth, self.expected.clone());
     }
 
+    /// Keep track of how long both backends took, once they're both done with
+    /// the current position.
+    fn record_timing(&mut self) {
+        let board = self.board_stack.last().unwrap();
+        let depth = self.remaining_depth();
+        let found = self.found.lock().unwrap().timing;
+        let expected = self.expected.lock().unwrap().timing;
+
+        let (Some(found), Some(expected)) = (found, expected) else { return };
+
+        let is_current = |timing: &Timing| timing.board == *board && timing.depth == depth;
+
+        if self.timing.is_some() || !is_current(&found) || !is_current(&expected) {
+            return;
+        }
+
+        self.timing = Some((found, expected));
+        self.timings.push(TimingRecord {
+            fen: self.variant.to_fen(board),
+            depth,
+            nodes: expected.nodes,
+            found_ms: found.elapsed.as_secs_f64() * 1000.0,
+            expected_ms: expected.elapsed.as_secs_f64() * 1000.0,
+        });
+    }
+
+    /// How long the backend under test took on the current position, every 
+    /// time it was searched, oldest first
+    fn timing_history(&self) -> Vec<f64> {
+        let fen = self.variant.to_fen(self.board_stack.last().unwrap());
+        let depth = self.remaining_depth();
+
+        self.timings
+            .iter()
+            .filter(|record| record.fen == fen && record.depth == depth)
+            .map(|record| record.found_ms)
+            .collect()
+    }
+
     fn refresh_diff(&mut self) -> anyhow::Result<()> {
-        let mut results: BTreeMap<String, Diff> = BTreeMap::new();
-
-        let expected = self.found.lock().unwrap();
-        let found = self.expected.lock().unwrap();
-
-        // Insert all of our moves, keyed by their algebraic string
-        for (mv, count) in found.iter() {
-            results.insert(
-                mv.to_string(),
-                Diff {
-                    mv: *mv,
-                    found: Some(*count),
-                    expected: None,
-                },
-            );
-        }
-
-        // Fill in any moves that Simbelmyne also found
-        for (mv, count) in expected.iter() {
-            let diff = results.entry(mv.to_string()).or_insert(Diff {
-                mv: *mv,
-                found: None,
-                expected: None,
-            });
+        self.record_timing();
+
+        let board = self.board_stack.last().unwrap();
+        let found = self.found.lock().unwrap();
+        let expected = self.expected.lock().unwrap();
+        let mut diffs = compare_results(board, &found.moves, &expected.moves, &self.reference);
 
-            diff.expected = Some(*count);
+        self.warnings = found.warnings
+            .iter()
+            .chain(&expected.warnings)
+            .cloned()
+            .collect();
+
+        // The totals should cover every move, not just the ones being shown
+        self.total_found = diffs.iter().map(Diff::found_total).sum();
+        self.total_expected = diffs.iter().map(Diff::expected_total).sum();
+
+        if self.mismatches_only {
+            diffs.retain(|diff| diff.is_mismatch());
+        }
+
+        let query = self.search.value();
+        if !query.is_empty() {
+            diffs.retain(|diff| matches_query(board, self.variant, diff, query));
+        }
+
+        self.sort.sort(&mut diffs);
+        self.diffs = diffs;
+
+        if let Some(mv) = self.restore_selection {
+            if let Some(idx) = self.diffs.iter().position(|diff| diff.mv == mv) {
+                self.selected = idx;
+                self.restore_selection = None;
+            }
         }
 
-        self.diffs = results.into_values().collect();
         Ok(())
     }
+
+    /// Rebuild the table after changing how it's sorted or filtered, keeping 
+    /// the same move selected if it's still in there.
+    fn reorder(&mut self) {
+        let selected = self.diffs.get(self.selected).map(|diff| diff.mv);
+        self.refresh_diff().unwrap();
+
+        self.selected = selected
+            .and_then(|mv| self.diffs.iter().position(|diff| diff.mv == mv))
+            .unwrap_or(0);
+    }
+
+    /// The moves differ from one position to the next, so don't carry the 
+    /// search query along.
+    fn clear_search(&mut self) {
+        self.search.reset();
+        self.searching = false;
+    }
+
+    /// Select the next mismatching row after the current one, wrapping around 
+    /// to the top of the table.
+    fn next_mismatch(&mut self) {
+        let len = self.diffs.len();
+
+        let next = (1..=len)
+            .map(|offset| (self.selected + offset) % len)
+            .find(|&idx| self.diffs[idx].is_mismatch());
+
+        if let Some(idx) = next {
+            self.selected = idx;
+        }
+    }
+
+    /// Select or play moves by clicking on the move table or the board
+    fn click(&mut self, position: Position) -> Option<Message> {
+        let layout = self.layout?;
+        let len = self.diffs.len();
+
+        if let Some(idx) = DiffTable::row_at(layout.table, self.table_offset, len, position) {
+            return self.click_row(idx);
+        }
+
+        if let Some(sq) = BoardView::square_at(layout.board, self.is_flipped(), position) {
+            return self.click_square(sq);
+        }
+
+        None
+    }
+
+    /// Select the clicked row, or play its move when it's clicked twice in
+    /// quick succession.
+    fn click_row(&mut self, idx: usize) -> Option<Message> {
+        let now = Instant::now();
+        let double_click = self.last_click
+            .is_some_and(|(row, time)| row == idx && now - time < DOUBLE_CLICK);
+
+        self.selected = idx;
+        self.pending = None;
+
+        if double_click {
+            self.last_click = None;
+
+            // Not `Message::Select`, which jumps to the selected bookmark
+            // while the bookmarks are listed
+            Some(Message::Play(self.diffs[idx].mv))
+        } else {
+            self.last_click = Some((idx, now));
+            None
+        }
+    }
+
+    /// The first click picks the piece to move, the second one the square to
+    /// move it to.
+    fn click_square(&mut self, sq: Square) -> Option<Message> {
+        let board = *self.board_stack.last().unwrap();
+
+        match self.pending.take() {
+            // Clicking the same square again puts the piece back down
+            Some(src) if src == sq => return None,
+
+            Some(src) => {
+                if let Some(mv) = self.find_move(src, sq) {
+                    return Some(Message::Play(mv));
+                }
+            }
+
+            None => {}
+        }
+
+        let own_piece = board.get_at(sq).is_some_and(|piece| piece.color() == board.current);
+        let first_move = self.diffs.iter().position(|diff| diff.mv.src() == sq);
+
+        if own_piece || first_move.is_some() {
+            self.pending = Some(sq);
+        }
+
+        // Point out the moves for the picked up piece in the table
+        if let Some(idx) = first_move {
+            self.selected = idx;
+        }
+
+        None
+    }
+
+    /// The legal move between the two squares, if any. Promotions are to a 
+    /// queen.
+    fn find_move(&self, src: Square, tgt: Square) -> Option<Move> {
+        let board = self.board_stack.last().unwrap();
+        let legal = self.variant.legal_moves(board);
+
+        let found = legal
+            .iter()
+            .copied()
+            .filter(|mv| mv.src() == src && mv.tgt() == tgt)
+            .min_by_key(|mv| mv.is_promotion() && mv.get_promo_type() != Some(PieceType::Queen));
+
+        // Castles can also be clicked in the other notation
+        found.or_else(|| {
+            let castle = self.variant.normalize(board, Move::new(src, tgt, MoveType::Quiet));
+            legal.contains(&castle).then_some(castle)
+        })
+    }
+
+    /// Whether the board should currently be drawn from Black's perspective
+    fn is_flipped(&self) -> bool {
+        let current_board = self.board_stack.last().unwrap();
+        self.flipped ^ (self.orient_to_move && current_board.current.is_black())
+    }
+
+    fn board_view(&self) -> BoardView {
+        BoardView {
+            board: *self.board_stack.last().unwrap(),
+            variant: self.variant,
+            highlight: self.diffs.get(self.selected).map(|diff| diff.mv),
+            pending: self.pending,
+            overlays: self.overlays,
+            flipped: self.is_flipped(),
+            theme: self.theme,
+            pieces: self.pieces,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
-enum Message {
+pub enum Message {
     Up,
     Down,
     Select,
     Back,
+    Toggle(Overlay),
+    ToggleMismatchesOnly,
+    CycleSort,
+    NextMismatch,
+    StartSearch,
+    Search(InputRequest),
+    ConfirmSearch,
+    CancelSearch,
+    Flip,
+    ToggleOrientation,
+    ToggleHelp,
+    PageUp,
+    PageDown,
+    First,
+    Last,
+    Click(Position),
+    Play(Move),
+    SaveSession,
+    EditNotes,
+    AddBookmark,
+    ToggleBookmarks,
+    DeleteBookmark,
+    ExportBookmarks,
+    Copy(Clip),
+    ExportReport,
+    Prompt(InputRequest),
+    ConfirmPrompt,
+    CancelPrompt,
     Quit,
 }
 
 fn view(state: &mut State, f: &mut Frame) {
     let term_rect = f.area();
     let layout = create_layout(term_rect);
+    state.layout = Some(layout);
+    state.table_offset = DiffTable::scroll(layout.table, state.selected, state.table_offset);
     let current_board = state.board_stack.last().unwrap();
 
     let move_table = DiffTable {
+        board: *current_board,
+        variant: state.variant,
         diffs: state.diffs.clone(),
         selected: state.selected,
+        offset: state.table_offset,
+        sort: state.sort,
+        mismatches_only: state.mismatches_only,
+        search: Some(state.search.value().to_owned())
+            .filter(|query| state.searching || !query.is_empty()),
+        searching: state.searching,
+        theme: state.theme,
     };
 
-    let board_view = BoardView {
-        board: *current_board,
-        highlight: state.diffs.get(state.selected).map(|diff| diff.mv),
+    let board_view = state.board_view();
+
+    let selected_diff = state.diffs.get(state.selected);
+
+    let detail_view = DetailView {
+        mv: selected_diff.map(|diff| diff.mv.to_string()),
+        explanation: selected_diff.map(|diff| explain(current_board, state.variant, diff)),
+        warnings: state.warnings.clone(),
+        theme: state.theme,
+    };
+
+    let bookmark_view = BookmarkView {
+        bookmarks: state.bookmarks.clone(),
+        selected: state.selected_bookmark,
+        theme: state.theme,
     };
 
     let info_view = InfoView {
-        starting_pos: state.initial_board.to_fen(),
-        current_pos: current_board.to_fen(),
+        starting_pos: state.variant.to_fen(&state.initial_board),
+        current_pos: state.variant.to_fen(current_board),
         search_depth: state.depth,
-        current_depth: state.board_stack.len(),
-        total_found: state.diffs.iter().map(|d| d.found.unwrap_or(0)).sum(),
-        total_expected: state.diffs.iter().map(|d| d.expected.unwrap_or(0)).sum(),
+        current_depth: state.path.len(),
+        total_found: state.total_found,
+        total_expected: state.total_expected,
+        timing: state.timing,
+        history: state.timing_history(),
+        notes: state.notes.clone(),
+        theme: state.theme,
     };
 
-    let help = Text::from(
-        Line::from(vec![
-            Span::styled("k ", Style::new().fg(Color::Blue)),
-            Span::styled("Up, ", Style::new().fg(Color::DarkGray)),
-            Span::styled("j ", Style::new().fg(Color::Blue)),
-            Span::styled("Down, ", Style::new().fg(Color::DarkGray)),
-            Span::styled("l ", Style::new().fg(Color::Blue)),
-            Span::styled("Select, ", Style::new().fg(Color::DarkGray)),
-            Span::styled("h ", Style::new().fg(Color::Blue)),
-            Span::styled("Back, ", Style::new().fg(Color::DarkGray)),
-            Span::styled("q ", Style::new().fg(Color::Blue)),
-            Span::styled("Quit, ", Style::new().fg(Color::DarkGray)),
-        ])
+    let key = Style::new().fg(state.theme.accent);
+    let label = Style::new().fg(state.theme.muted);
+
+    let overlays = [
+        Overlay::Attacked,
+        Overlay::Pins,
+        Overlay::Checkers,
+        Overlay::EnPassant,
+        Overlay::Castling,
+    ];
+
+    let hints = [
+        (vec![Message::ToggleHelp], "Help"),
+        (vec![Message::Up], "Up"),
+        (vec![Message::Down], "Down"),
+        (vec![Message::Select], "Select"),
+        (vec![Message::Back], "Back"),
+        (vec![Message::StartSearch], "Search"),
+        (vec![Message::NextMismatch], "Next mismatch"),
+        (vec![Message::ToggleMismatchesOnly], "Mismatches only"),
+        (vec![Message::CycleSort], "Sort"),
+        (overlays.map(Message::Toggle).to_vec(), "Overlays"),
+        (vec![Message::Flip], "Flip"),
+        (vec![Message::ToggleOrientation], "Orient to move"),
+        (vec![Message::ToggleBookmarks], "Bookmarks"),
+        (vec![Message::SaveSession], "Save session"),
+        (vec![Message::Quit], "Quit"),
+    ];
+
+    // Show the first key bound to each message, skipping unbound ones
+    let hints = Line::from(
+        hints
+            .into_iter()
+            .filter_map(|(messages, hint)| {
+                let keys = messages
+                    .into_iter()
+                    .map(|message| state.keymap.keys_for(message).first().map(Key::to_string))
+                    .collect::<Option<Vec<_>>>()?;
+
+                Some([
+                    Span::styled(format!("{} ", keys.join("/")), key),
+                    Span::styled(format!("{hint}, "), label),
+                ])
+            })
+            .flatten()
+            .collect::<Vec<_>>(),
     );
 
+    let help = match (state.prompt, &state.status) {
+        (Some(prompt), _) => Text::from(Line::from(vec![
+            Span::styled(format!("{}: ", prompt.label()), key),
+            Span::raw(format!("{}_", state.prompt_input.value())),
+        ])),
+        (None, Some(status)) => Text::from(Span::styled(status.clone(), label)),
+        (None, None) => Text::from(hints),
+    };
+
     f.render_widget(move_table, layout.table);
     f.render_widget(board_view, layout.board);
+    if state.show_bookmarks {
+        f.render_widget(bookmark_view, layout.detail);
+    } else {
+        f.render_widget(detail_view, layout.detail);
+    }
+
     f.render_widget(info_view, layout.info);
     f.render_widget(help, layout.help);
+
+    if state.show_help {
+        let help_view = HelpView {
+            keymap: state.keymap.clone(),
+            theme: state.theme,
+        };
+
+        f.render_widget(help_view, term_rect);
+    }
 }
 
+#[derive(Debug, Clone, Copy)]
 struct LayoutChunks {
     table: Rect,
     board: Rect,
+    detail: Rect,
     info: Rect,
     help: Rect,
 }
 
-fn create_layout(container: Rect) -> LayoutChunks {
-    let app_width = 130;
-    let app_height = 45;
+/// Terminals at least this wide get the table, board and details side by side
+const WIDE_LAYOUT: u16 = 130;
+
+/// Terminals narrower than this get all the panels stacked vertically
+const NARROW_LAYOUT: u16 = 90;
 
-    let centered_rect = centered(container, app_width, app_height);
+/// The width of the column holding the move table
+const TABLE_WIDTH: u16 = 57;
+
+/// Only show the information panel if there's enough room left for the board
+const INFO_MIN_HEIGHT: u16 = 30;
+
+/// When stacking the panels, the information panel needs even more room
+const STACKED_INFO_MIN_HEIGHT: u16 = 50;
+
+fn create_layout(container: Rect) -> LayoutChunks {
+    let narrow = container.width < NARROW_LAYOUT;
+    let info_min_height = if narrow { STACKED_INFO_MIN_HEIGHT } else { INFO_MIN_HEIGHT };
+    let info_height = if container.height >= info_min_height { 11 } else { 0 };
 
     let sections = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Max(34), Constraint::Max(10), Constraint::Max(1)])
-        .split(centered_rect);
-
-    let top_panel = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Max(35), Constraint::Max(130)])
-        .split(sections[0]);
+        .constraints([
+            Constraint::Min(0), 
+            Constraint::Length(info_height), 
+            Constraint::Length(1)
+        ])
+        .split(container);
 
+    let main_panel = sections[0];
     let bottom_panel = sections[1];
     let help_area = sections[2];
 
-    let table_panel = top_panel[0];
-    let board_panel = top_panel[1];
+    // Everything stacked on top of one another
+    if narrow {
+        // Give the board as much room as it can use, but leave some room for
+        // the table.
+        let board_height = BoardView::preferred_height(main_panel.width)
+            .min(main_panel.height * 3 / 5);
+
+        let stacked = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(board_height),
+                Constraint::Min(5),
+                Constraint::Length(6),
+            ])
+            .split(main_panel);
+
+        return LayoutChunks {
+            board: stacked[0],
+            table: stacked[1],
+            detail: stacked[2],
+            info: bottom_panel,
+            help: help_area,
+        };
+    }
+
+    // Table and details share a column, next to the board
+    if container.width < WIDE_LAYOUT {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(TABLE_WIDTH), Constraint::Min(0)])
+            .split(main_panel);
+
+        let left_column = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(columns[0]);
+
+        return LayoutChunks {
+            table: left_column[0],
+            detail: left_column[1],
+            board: columns[1],
+            info: bottom_panel,
+            help: help_area,
+        };
+    }
+
+    let top_panel = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(TABLE_WIDTH), Constraint::Min(0), Constraint::Length(40)])
+        .split(main_panel);
 
     LayoutChunks {
-        table: table_panel,
-        board: board_panel,
+        table: top_panel[0],
+        board: top_panel[1],
+        detail: top_panel[2],
         info: bottom_panel,
         help: help_area,
     }
@@ -205,25 +1025,50 @@ fn create_layout(container: Rect) -> LayoutChunks {
 fn initialize_panic_handler() {
     let original_hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |panic_info| {
-        crossterm::execute!(std::io::stderr(), crossterm::terminal::LeaveAlternateScreen).unwrap();
+        // Panics in the backends are caught on their own threads, and show up
+        // as warnings, so leave the terminal alone for those.
+        if std::thread::current().name() != Some("main") {
+            return;
+        }
+
+        crossterm::execute!(
+            std::io::stderr(), 
+            crossterm::event::DisableMouseCapture,
+            crossterm::terminal::LeaveAlternateScreen
+        ).unwrap();
         crossterm::terminal::disable_raw_mode().unwrap();
         original_hook(panic_info);
     }));
 }
 
-fn handle_event(_: &State) -> anyhow::Result<Option<Message>> {
+fn handle_event(state: &State) -> anyhow::Result<Option<Message>> {
     let message = if crossterm::event::poll(std::time::Duration::from_millis(16))? {
-        if let crossterm::event::Event::Key(key) = crossterm::event::read()? {
-            match key.code {
-                KeyCode::Char('j') => Message::Down,
-                KeyCode::Char('k') => Message::Up,
-                KeyCode::Char('q') | KeyCode::Esc => Message::Quit,
-                KeyCode::Char('h') => Message::Back,
-                KeyCode::Char('l') | KeyCode::Enter => Message::Select,
-                _ => return Ok(None),
-            }
-        } else {
-            return Ok(None);
+        let event = crossterm::event::read()?;
+
+        if state.searching {
+            return Ok(handle_search_event(state, &event));
+        }
+
+        if state.prompt.is_some() {
+            return Ok(handle_prompt_event(state, &event));
+        }
+
+        let key = match event {
+            Event::Key(key) => key,
+            Event::Mouse(_) if state.show_help => return Ok(None),
+            Event::Mouse(mouse) => return Ok(handle_mouse_event(mouse)),
+            _ => return Ok(None),
+        };
+
+        match state.keymap.get(Key::from(key)) {
+            // The help modal swallows every other key
+            Some(Message::ToggleHelp | Message::Quit) if state.show_help => Message::ToggleHelp,
+            _ if state.show_help => return Ok(None),
+
+            Some(Message::Quit) if state.show_bookmarks => Message::ToggleBookmarks,
+            Some(Message::Quit) if !state.search.value().is_empty() => Message::CancelSearch,
+            Some(message) => message,
+            None => return Ok(None),
         }
     } else {
         return Ok(None);
@@ -232,8 +1077,76 @@ fn handle_event(_: &State) -> anyhow::Result<Option<Message>> {
     Ok(Some(message))
 }
 
+/// While typing in the prompt, most keys edit the text instead. The prompt is
+/// confirmed and cancelled with the same keys as a search.
+fn handle_prompt_event(state: &State, event: &Event) -> Option<Message> {
+    if let Event::Key(key) = event {
+        match state.keymap.get_search(Key::from(*key)) {
+            Some(Message::ConfirmSearch) => return Some(Message::ConfirmPrompt),
+            Some(Message::CancelSearch) => return Some(Message::CancelPrompt),
+            _ => {}
+        }
+    }
+
+    to_input_request(event).map(Message::Prompt)
+}
+
+/// Clicks are resolved against the layout in `update`, the scroll wheel moves
+/// the selection.
+fn handle_mouse_event(mouse: crossterm::event::MouseEvent) -> Option<Message> {
+    match mouse.kind {
+        MouseEventKind::Down(MouseButton::Left) => {
+            Some(Message::Click(Position::new(mouse.column, mouse.row)))
+        }
+        MouseEventKind::ScrollUp => Some(Message::Up),
+        MouseEventKind::ScrollDown => Some(Message::Down),
+        _ => None,
+    }
+}
+
+/// While typing a search query, most keys edit the query instead
+fn handle_search_event(state: &State, event: &Event) -> Option<Message> {
+    if let Event::Key(key) = event {
+        if let Some(message) = state.keymap.get_search(Key::from(*key)) {
+            return Some(message);
+        }
+    }
+
+    to_input_request(event).map(Message::Search)
+}
+
+fn parse_move(mv: &str) -> anyhow::Result<Move> {
+    mv.parse().map_err(|_| anyhow!("Invalid move '{mv}'"))
+}
+
+/// How far PageUp and PageDown move the selection
+const PAGE_SIZE: usize = 10;
+
+/// The longest gap between two clicks on a row that still plays its move
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
 fn update(state: &mut State, message: Message) -> Option<Message> {
+    state.status = None;
+
     match message {
+        // While the bookmarks are open, the selection moves through those
+        Message::Up if state.show_bookmarks => {
+            state.selected_bookmark = state.selected_bookmark.saturating_sub(1)
+        }
+
+        Message::Down if state.show_bookmarks => {
+            state.selected_bookmark = 
+                (state.selected_bookmark + 1).min(state.bookmarks.len().saturating_sub(1))
+        }
+
+        Message::First if state.show_bookmarks => state.selected_bookmark = 0,
+
+        Message::Last if state.show_bookmarks => {
+            state.selected_bookmark = state.bookmarks.len().saturating_sub(1)
+        }
+
+        Message::Select if state.show_bookmarks => state.jump_to_bookmark(),
+
         Message::Up => {
             if 0 < state.selected {
                 state.selected -= 1
@@ -241,29 +1154,128 @@ fn update(state: &mut State, message: Message) -> Option<Message> {
         }
 
         Message::Down => {
-            if state.selected < state.diffs.len() - 1 {
+            if state.selected + 1 < state.diffs.len() {
                 state.selected += 1
             }
         }
 
+        Message::PageUp => state.selected = state.selected.saturating_sub(PAGE_SIZE),
+
+        Message::PageDown => {
+            state.selected = (state.selected + PAGE_SIZE).min(state.diffs.len().saturating_sub(1))
+        }
+
+        Message::First => state.selected = 0,
+
+        Message::Last => state.selected = state.diffs.len().saturating_sub(1),
+
         Message::Quit => state.should_quit = true,
 
-        Message::Select => {
-            let current_depth = state.board_stack.len();
+        Message::ToggleHelp => state.show_help = !state.show_help,
 
-            if current_depth + 1 == state.depth {
-                return None;
+        Message::Toggle(overlay) => state.overlays.toggle(overlay),
+
+        Message::ToggleMismatchesOnly => {
+            state.mismatches_only = !state.mismatches_only;
+            state.reorder();
+        }
+
+        Message::CycleSort => {
+            state.sort = state.sort.next();
+            state.reorder();
+        }
+
+        Message::NextMismatch => state.next_mismatch(),
+
+        Message::StartSearch => state.searching = true,
+
+        Message::Search(request) => {
+            state.search.handle(request);
+            state.reorder();
+        }
+
+        Message::ConfirmSearch => state.searching = false,
+
+        Message::CancelSearch => {
+            state.clear_search();
+            state.reorder();
+        }
+
+        Message::Flip => state.flipped = !state.flipped,
+
+        Message::ToggleOrientation => state.orient_to_move = !state.orient_to_move,
+
+        Message::Click(position) => return state.click(position),
+
+        Message::SaveSession => state.save_session(),
+
+        Message::EditNotes => {
+            state.prompt = Some(Prompt::Notes);
+            state.prompt_input = Input::new(state.notes.clone());
+        }
+
+        Message::AddBookmark => {
+            state.prompt = Some(Prompt::Bookmark);
+            state.prompt_input = Input::default();
+        }
+
+        Message::ToggleBookmarks => state.show_bookmarks = !state.show_bookmarks,
+
+        Message::DeleteBookmark if state.show_bookmarks => state.delete_bookmark(),
+
+        Message::DeleteBookmark => {}
+
+        Message::ExportBookmarks => state.export_bookmarks(),
+
+        Message::Copy(clip) => state.copy(clip),
+
+        Message::ExportReport => state.export_report(),
+
+        Message::Prompt(request) => {
+            state.prompt_input.handle(request);
+        }
+
+        Message::ConfirmPrompt => {
+            let value = state.prompt_input.value().trim().to_owned();
+
+            match state.prompt.take() {
+                Some(Prompt::Notes) => state.notes = value,
+                Some(Prompt::Bookmark) => state.add_bookmark(value),
+                None => {}
             }
+        }
+
+        Message::CancelPrompt => state.prompt = None,
 
+        // Nothing to descend into if the table is empty
+        Message::Select => return Some(Message::Play(state.diffs.get(state.selected)?.mv)),
+
+        // Don't descend past the last ply, there'd be nothing left to split
+        Message::Play(_) if state.remaining_depth() <= 1 => {}
+
+        Message::Play(mv) => {
             let current_board = state.board_stack.last().unwrap();
-            let selected_move = state.diffs[state.selected].mv;
 
-            let new_board = current_board.play_move(selected_move);
+            // There's no position to descend into for a move that isn't even
+            // pseudo-legal, e.g., a move from an empty square.
+            if let Some(reason) = why_invalid(current_board, state.variant, mv) {
+                state.status = Some(format!("Can't play {mv}: {reason}"));
+                return None;
+            }
+
+            // An illegal castle would be played as a plain king move, onto
+            // our own rook or past it.
+            if is_castle_attempt(current_board, state.variant, mv) 
+                && !state.variant.legal_moves(current_board).contains(&mv) {
+                state.status = Some(format!("Can't play {mv}: castling isn't legal here."));
+                return None;
+            }
+
+            let new_board = state.variant.play_move(current_board, mv);
 
             state.board_stack.push(new_board);
-            state.run_perft();
-            state.refresh_diff().unwrap();
-            state.selected = 0;
+            state.path.push(mv);
+            state.enter_position();
         }
 
         Message::Back => {
@@ -273,9 +1285,8 @@ fn update(state: &mut State, message: Message) -> Option<Message> {
             }
 
             state.board_stack.pop();
-            state.run_perft();
-            state.refresh_diff().unwrap();
-            state.selected = 0;
+            state.path.pop();
+            state.enter_position();
         }
     }
 
@@ -284,27 +1295,50 @@ fn update(state: &mut State, message: Message) -> Option<Message> {
 
 impl Config {
     pub fn run(&self) -> anyhow::Result<()> {
+    let file_config = self.file_config()?;
+    let session = self.session()?;
+    let (board, variant) = self.position(&file_config, session.as_ref())?;
+    let profile = self.profile(&file_config, session.as_ref())?;
+    let engine = PerftThread::new(Self::backend(&profile, variant)?);
+    let (theme, pieces) = self.appearance(&file_config);
+    let keymap = self.keymap(&file_config);
+    let session_path = self.session.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_SESSION));
+
+    let mut state = State::new(
+        self.depth(&file_config, session.as_ref())?,
+        board,
+        variant,
+        engine,
+        self.orient_to_move(&file_config),
+        theme,
+        pieces,
+    )
+    .with_keymap(keymap)
+    .with_session(session_path, profile)
+    .with_clipboard(file_config.clipboard.unwrap_or_default())
+    .with_reference(self.reference(&file_config, variant)?);
+
+    if let Some(session) = &session {
+        state.restore(session)?;
+    }
+
     initialize_panic_handler();
 
     // Startup
     crossterm::terminal::enable_raw_mode()?;
-    crossterm::execute!(std::io::stderr(), crossterm::terminal::EnterAlternateScreen)?;
+    crossterm::execute!(
+        std::io::stderr(), 
+        crossterm::terminal::EnterAlternateScreen,
+        crossterm::event::EnableMouseCapture
+    )?;
 
     let mut terminal = Terminal::new(CrosstermBackend::new(std::io::stderr()))?;
 
-    let engine = if let Some(engine) = &self.engine {
-            PerftThread::new(Engine::new(engine.to_path_buf()).unwrap())
-        } else if let Some(command) = &self.command {
-            PerftThread::new(Executable::new(command.to_path_buf()))
-        } else {
-            panic!()
-        };
-
-    let mut state = State::new(self.depth, self.fen.to_string(), engine);
     state.run_perft();
 
     loop {
         state.refresh_diff().unwrap();
+        state.poll_export();
 
         // Render the current view
         terminal.draw(|f| {
@@ -326,7 +1360,11 @@ impl Config {
     }
 
     // Shutdown
-    crossterm::execute!(std::io::stderr(), crossterm::terminal::LeaveAlternateScreen)?;
+    crossterm::execute!(
+        std::io::stderr(), 
+        crossterm::event::DisableMouseCapture,
+        crossterm::terminal::LeaveAlternateScreen
+    )?;
     crossterm::terminal::disable_raw_mode()?;
     Ok(())
 
@@ -0,0 +1,467 @@
+//! Chess960 (and Double Fischer Random Chess) on top of the reference board.
+//!
+//! `Board` only knows about standard castling: it assumes the kings start on
+//! the e-file and the rooks in the corners. The castling rights themselves
+//! work the same way in Chess960 (once a king or rook moves, they're gone for
+//! good), so we keep tracking them in `Board::castling_rights`, and separately
+//! remember the squares the kings and rooks castle from. Those never change
+//! over the course of a game.
+//!
+//! Chess960 castles are encoded the way UCI does in Chess960 mode: as the king
+//! capturing its own rook (e.g., `b1a1`, `e8h8`).
+
+use anyhow::anyhow;
+use simbelmyne_chess::bitboard::Bitboard;
+use simbelmyne_chess::board::Board;
+use simbelmyne_chess::movegen::castling::{CastleType, CastlingRights};
+use simbelmyne_chess::movegen::legal_moves::MoveList;
+use simbelmyne_chess::movegen::lookups::BETWEEN;
+use simbelmyne_chess::movegen::moves::{Move, MoveType};
+use simbelmyne_chess::piece::{Color, Piece, PieceType};
+use simbelmyne_chess::square::Square;
+use serde::{Deserialize, Serialize};
+
+/// The rules to generate and play moves by
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Variant {
+    #[default]
+    Standard,
+    Chess960(Chess960),
+}
+
+/// How to write the castling rights of a Chess960 position in a FEN
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CastlingNotation {
+    /// The files of the castling rooks, e.g., `HBhb`
+    #[default]
+    Shredder,
+
+    /// `KQkq` for the outermost rooks, and files for any other rook
+    XFen,
+}
+
+/// The squares the kings and rooks castle from in a Chess960 game
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Chess960 {
+    /// The king's starting square, indexed by color
+    kings: [Square; 2],
+
+    /// The castling rook's starting square, indexed by castle type
+    rooks: [Square; 4],
+}
+
+impl Variant {
+    /// Parse a FEN string. In Chess960, the castling rights can be given as
+    /// Shredder-FEN (rook files, e.g., `HAha`) or X-FEN (`KQkq` for the
+    /// outermost rooks, files for any other rook).
+    pub fn parse_fen(fen: &str, chess960: bool) -> anyhow::Result<(Board, Variant)> {
+        if !chess960 {
+            return Ok((fen.parse()?, Variant::Standard));
+        }
+
+        let mut fields = fen.split_whitespace().collect::<Vec<_>>();
+        let castling = fields.get(2).copied().unwrap_or("-");
+
+        // Let the reference parse everything but the castling rights
+        if fields.len() > 2 {
+            fields[2] = "-";
+        }
+
+        let mut board: Board = fields.join(" ").parse()?;
+        let (rights, chess960) = Chess960::parse_castling(&board, castling)?;
+        board.castling_rights = rights;
+
+        Ok((board, Variant::Chess960(chess960)))
+    }
+
+    /// Write out the FEN for a board. In Chess960, the castling rights are
+    /// written as Shredder-FEN.
+    pub fn to_fen(&self, board: &Board) -> String {
+        self.to_fen_as(board, CastlingNotation::Shredder)
+    }
+
+    /// Write out the FEN for a board, with the castling rights of a Chess960 
+    /// position written in the given notation.
+    pub fn to_fen_as(&self, board: &Board, notation: CastlingNotation) -> String {
+        let fen = board.to_fen();
+
+        match self {
+            Variant::Standard => fen,
+            Variant::Chess960(chess960) => {
+                let mut fields = fen.split_whitespace().map(String::from).collect::<Vec<_>>();
+                fields[2] = match notation {
+                    CastlingNotation::Shredder => chess960.shredder_castling(board.castling_rights),
+                    CastlingNotation::XFen => chess960.xfen_castling(board),
+                };
+                fields.join(" ")
+            }
+        }
+    }
+
+    /// The board as the standard move generator should see it. It would get
+    /// Chess960 castles wrong, so the castling rights are dropped.
+    pub fn standard_board(&self, board: &Board) -> Board {
+        match self {
+            Variant::Standard => *board,
+            Variant::Chess960(_) => Board { castling_rights: CastlingRights::none(), ..*board },
+        }
+    }
+
+    /// All the legal moves in the position, castles included
+    pub fn legal_moves(&self, board: &Board) -> MoveList {
+        let mut moves = self.standard_board(board).legal_moves::<true>();
+
+        if let Variant::Chess960(chess960) = self {
+            for ctype in board.castling_rights.get_available(board.current) {
+                if chess960.castle_allowed(board, ctype) {
+                    moves.push(chess960.castle_move(ctype));
+                }
+            }
+        }
+
+        moves
+    }
+
+    /// Play a move on the board
+    pub fn play_move(&self, board: &Board, mv: Move) -> Board {
+        match self {
+            Variant::Standard => board.play_move(mv),
+            Variant::Chess960(chess960) if mv.is_castle() => chess960.play_castle(board, mv),
+            Variant::Chess960(chess960) => {
+                let mut new_board = self.standard_board(board).play_move(mv);
+                new_board.castling_rights = chess960.revoke(board.castling_rights, mv);
+                new_board
+            }
+        }
+    }
+
+    /// The squares of the kings and rooks that can still castle
+    pub fn castling_squares(&self, board: &Board) -> Bitboard {
+        board.castling_rights
+            .flat_map(|ctype| {
+                let (king, rook) = self.castle_squares(ctype);
+                [king, rook]
+            })
+            .collect()
+    }
+
+    /// The squares the king and rook castle from
+    pub fn castle_squares(&self, ctype: CastleType) -> (Square, Square) {
+        match self {
+            Variant::Standard => (ctype.king_move().src(), ctype.rook_move().src()),
+            Variant::Chess960(chess960) => (chess960.king(ctype), chess960.rook(ctype)),
+        }
+    }
+
+    /// The castling rights as written in the FEN, e.g., `KQkq` or `HBhb`
+    pub fn castling_rights(&self, board: &Board) -> String {
+        match self {
+            Variant::Standard => board.castling_rights.to_string(),
+            Variant::Chess960(chess960) => chess960.shredder_castling(board.castling_rights),
+        }
+    }
+
+    /// Find the legal move with the same source, target and promotion piece,
+    /// if there is one. This fills in all the metadata the bare move is
+    /// missing.
+    pub fn find_legal(&self, board: &Board, mv: Move) -> Option<Move> {
+        self.legal_moves(board).into_iter().find(|legal| {
+            legal.src() == mv.src()
+                && legal.tgt() == mv.tgt()
+                && legal.get_promo_type() == mv.get_promo_type()
+        })
+    }
+
+    /// Map a move, as written by some other move generator, onto the legal
+    /// move it stands for. This fills in the metadata (captures, promotions,
+    /// castles, ...) the bare move is missing, and maps a castle written in
+    /// the other notation (a two-square king move rather than the king
+    /// capturing its rook, or vice versa) onto the castle this variant
+    /// generates.
+    ///
+    /// Illegal moves, and castles that can't be told apart from a regular king
+    /// move, are returned as-is.
+    pub fn normalize(&self, board: &Board, mv: Move) -> Move {
+        if let Some(exact) = self.find_legal(board, mv) {
+            return exact;
+        }
+
+        self.legal_moves(board)
+            .iter()
+            .filter(|legal| legal.is_castle() && legal.src() == mv.src())
+            .find(|castle| {
+                let ctype = castle_type(board.current, **castle);
+
+                let alternative = match self {
+                    Variant::Standard => ctype.rook_move().src(),
+                    Variant::Chess960(_) => ctype.king_move().tgt(),
+                };
+
+                alternative == mv.tgt()
+            })
+            .copied()
+            .unwrap_or(mv)
+    }
+}
+
+impl Chess960 {
+    fn king(&self, ctype: CastleType) -> Square {
+        self.kings[ctype.color() as usize]
+    }
+
+    fn rook(&self, ctype: CastleType) -> Square {
+        self.rooks[ctype as usize]
+    }
+
+    fn castle_move(&self, ctype: CastleType) -> Move {
+        let mtype = match ctype {
+            CastleType::WK | CastleType::BK => MoveType::KingCastle,
+            CastleType::WQ | CastleType::BQ => MoveType::QueenCastle,
+        };
+
+        Move::new(self.king(ctype), self.rook(ctype), mtype)
+    }
+
+    /// Read the castling rights, and figure out which rook each of them refers
+    /// to.
+    fn parse_castling(board: &Board, castling: &str) -> anyhow::Result<(CastlingRights, Self)> {
+        use Square::*;
+        let mut rights = CastlingRights::none();
+        let mut chess960 = Self { kings: [E1, E8], rooks: [A1, H1, A8, H8] };
+
+        for ch in castling.chars().filter(|&ch| ch != '-') {
+            let color = if ch.is_ascii_uppercase() { Color::White } else { Color::Black };
+            let back_rank = if color.is_white() { 0 } else { 7 };
+
+            let king = board.kings(color)
+                .find(|sq| sq.rank() == back_rank)
+                .ok_or(anyhow!("Castling right '{ch}', but no king on the back rank"))?;
+
+            let mut rooks = board.rooks(color).filter(|sq| sq.rank() == back_rank);
+
+            let rook = match ch.to_ascii_lowercase() {
+                'k' => rooks.filter(|sq| sq.file() > king.file()).last(),
+                'q' => rooks.find(|sq| sq.file() < king.file()),
+                file @ 'a'..='h' => {
+                    let file = file as usize - 'a' as usize;
+                    rooks.find(|sq| sq.file() == file)
+                }
+                _ => Err(anyhow!("Invalid castling rights '{castling}'"))?,
+            };
+
+            let rook = rook.ok_or(anyhow!("Castling right '{ch}', but no rook to castle with"))?;
+
+            let ctype = side_castle_type(color, rook.file() > king.file());
+
+            rights.add(ctype);
+            chess960.kings[color as usize] = king;
+            chess960.rooks[ctype as usize] = rook;
+        }
+
+        Ok((rights, chess960))
+    }
+
+    /// Write the castling rights as Shredder-FEN, e.g., `HBhb`
+    fn shredder_castling(&self, rights: CastlingRights) -> String {
+        use CastleType::*;
+
+        let castling = [WK, WQ, BK, BQ]
+            .into_iter()
+            .filter(|&ctype| rights.is_available(ctype))
+            .map(|ctype| {
+                let file = Square::NAMES[self.rook(ctype) as usize].chars().next().unwrap();
+                if ctype.color().is_white() { file.to_ascii_uppercase() } else { file }
+            })
+            .collect::<String>();
+
+        if castling.is_empty() { String::from("-") } else { castling }
+    }
+
+    /// Write the castling rights as X-FEN, e.g., `KBkb`. The file is only 
+    /// given when there's another rook further out on the same side, so 
+    /// `KQkq` would be ambiguous.
+    fn xfen_castling(&self, board: &Board) -> String {
+        use CastleType::*;
+
+        let castling = [WK, WQ, BK, BQ]
+            .into_iter()
+            .filter(|&ctype| board.castling_rights.is_available(ctype))
+            .map(|ctype| {
+                let color = ctype.color();
+                let king = self.king(ctype);
+                let rook = self.rook(ctype);
+                let kingside = rook.file() > king.file();
+
+                let outermost = board.rooks(color)
+                    .filter(|sq| sq.rank() == rook.rank())
+                    .all(|sq| if kingside { sq.file() <= rook.file() } else { sq.file() >= rook.file() });
+
+                let ch = match (outermost, kingside) {
+                    (true, true) => 'k',
+                    (true, false) => 'q',
+                    (false, _) => Square::NAMES[rook as usize].chars().next().unwrap(),
+                };
+
+                if color.is_white() { ch.to_ascii_uppercase() } else { ch }
+            })
+            .collect::<String>();
+
+        if castling.is_empty() { String::from("-") } else { castling }
+    }
+
+    /// Castling is allowed if the king and rook are where they should be,
+    /// every square either of them passes through or lands on is empty (apart
+    /// from the king and rook themselves), none of the squares the king passes
+    /// through is attacked, and the king doesn't end up in check.
+    fn castle_allowed(&self, board: &Board, ctype: CastleType) -> bool {
+        let us = ctype.color();
+        let king = self.king(ctype);
+        let rook = self.rook(ctype);
+        let king_tgt = ctype.king_move().tgt();
+        let rook_tgt = ctype.rook_move().tgt();
+
+        if board.get_at(king) != Some(Piece::new(PieceType::King, us))
+            || board.get_at(rook) != Some(Piece::new(PieceType::Rook, us)) {
+            return false;
+        }
+
+        let king_path = BETWEEN[king as usize][king_tgt as usize]
+            | Bitboard::from(king)
+            | Bitboard::from(king_tgt);
+
+        let rook_path = BETWEEN[rook as usize][rook_tgt as usize] | Bitboard::from(rook_tgt);
+        let others = board.all_occupied() & !Bitboard::from(king) & !Bitboard::from(rook);
+
+        if !((king_path | rook_path) & others).is_empty() {
+            return false;
+        }
+
+        if !(king_path & board.attacked_by::<true>(!us)).is_empty() {
+            return false;
+        }
+
+        // Moving the rook out of the way can uncover an attack on the king's
+        // target square along the back rank.
+        let new_board = self.play_castle(board, self.castle_move(ctype));
+        new_board.compute_checkers(us).is_empty()
+    }
+
+    fn play_castle(&self, board: &Board, mv: Move) -> Board {
+        let us = board.current;
+        let ctype = castle_type(us, mv);
+        let mut new_board = *board;
+
+        let king = new_board.remove_at(mv.src()).unwrap();
+        let rook = new_board.remove_at(mv.tgt()).unwrap();
+        new_board.add_at(ctype.king_move().tgt(), king);
+        new_board.add_at(ctype.rook_move().tgt(), rook);
+
+        new_board.current = !us;
+        new_board.en_passant = None;
+        new_board.half_moves += 1;
+
+        if us.is_black() {
+            new_board.full_moves += 1;
+        }
+
+        new_board.castling_rights = self.revoke(board.castling_rights, mv);
+        new_board.pinrays = [
+            new_board.compute_pinrays(Color::White),
+            new_board.compute_pinrays(Color::Black),
+        ];
+        new_board.checkers = [
+            new_board.compute_checkers(Color::White),
+            new_board.compute_checkers(Color::Black),
+        ];
+
+        new_board
+    }
+
+    /// Drop the castling rights for any king or rook that moves or gets
+    /// captured.
+    fn revoke(&self, mut rights: CastlingRights, mv: Move) -> CastlingRights {
+        for ctype in CastleType::ALL {
+            let touched = [self.king(ctype), self.rook(ctype)];
+
+            if touched.contains(&mv.src()) || touched.contains(&mv.tgt()) {
+                rights.remove(ctype);
+            }
+        }
+
+        rights
+    }
+}
+
+/// The castle type for a castling move by the given side
+pub fn castle_type(us: Color, mv: Move) -> CastleType {
+    side_castle_type(us, mv.get_type() == MoveType::KingCastle)
+}
+
+/// The castle type for the given side, castling either towards the h-file
+/// (kingside) or towards the a-file (queenside)
+pub fn side_castle_type(us: Color, kingside: bool) -> CastleType {
+    match (us, kingside) {
+        (Color::White, true) => CastleType::WK,
+        (Color::White, false) => CastleType::WQ,
+        (Color::Black, true) => CastleType::BK,
+        (Color::Black, false) => CastleType::BQ,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FEN: &str = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+
+    fn normalize(chess960: bool, mv: &str) -> Move {
+        let (board, variant) = Variant::parse_fen(FEN, chess960).unwrap();
+        variant.normalize(&board, mv.parse().unwrap())
+    }
+
+    #[test]
+    fn normalize_standard_castles() {
+        for (mv, castle, mtype) in [
+            ("e1g1", "e1g1", MoveType::KingCastle),
+            ("e1h1", "e1g1", MoveType::KingCastle),
+            ("e1c1", "e1c1", MoveType::QueenCastle),
+            ("e1a1", "e1c1", MoveType::QueenCastle),
+        ] {
+            let normalized = normalize(false, mv);
+            assert_eq!(normalized.to_string(), castle, "{mv}");
+            assert_eq!(normalized.get_type(), mtype, "{mv}");
+        }
+    }
+
+    #[test]
+    fn normalize_chess960_castles() {
+        for (mv, castle, mtype) in [
+            ("e1g1", "e1h1", MoveType::KingCastle),
+            ("e1h1", "e1h1", MoveType::KingCastle),
+            ("e1c1", "e1a1", MoveType::QueenCastle),
+            ("e1a1", "e1a1", MoveType::QueenCastle),
+        ] {
+            let normalized = normalize(true, mv);
+            assert_eq!(normalized.to_string(), castle, "{mv}");
+            assert_eq!(normalized.get_type(), mtype, "{mv}");
+        }
+    }
+
+    #[test]
+    fn normalize_fills_in_metadata() {
+        let (board, variant) = Variant::parse_fen("4k3/1P6/8/8/8/8/4P3/4K3 w - - 0 1", false).unwrap();
+
+        let push = variant.normalize(&board, "e2e4".parse().unwrap());
+        assert_eq!(push.get_type(), MoveType::DoublePush);
+
+        let promo = variant.normalize(&board, "b7b8n".parse().unwrap());
+        assert_eq!(promo.get_promo_type(), Some(PieceType::Knight));
+    }
+
+    #[test]
+    fn normalize_keeps_illegal_moves() {
+        let illegal: Move = "e1e3".parse().unwrap();
+        assert_eq!(normalize(false, "e1e3"), illegal);
+    }
+}
@@ -0,0 +1,33 @@
+//! Perft counts for a few well-known Chess960 positions, to catch regressions
+//! in the castling rules the reference adds on top of the standard move
+//! generator.
+
+use pretty_perft::perft::perft;
+use pretty_perft::variant::Variant;
+
+/// Positions and their node counts at depths 1 through 4
+const POSITIONS: [(&str, [usize; 4]); 3] = [
+    (
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        [21, 528, 12189, 326672],
+    ),
+    (
+        "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+        [21, 807, 18002, 667366],
+    ),
+    (
+        "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9",
+        [20, 479, 10471, 273318],
+    ),
+];
+
+#[test]
+fn chess960_perft() {
+    for (fen, counts) in POSITIONS {
+        let (board, variant) = Variant::parse_fen(fen, true).unwrap();
+
+        for (depth, &count) in (1..).zip(&counts) {
+            assert_eq!(perft(board, depth, variant), count, "{fen} at depth {depth}");
+        }
+    }
+}